@@ -96,4 +96,46 @@ def generateScene(_items, _location, _description, _question, _history):
         config={"configurable": {"thread_id": "2"}},
     )
 
-    return response["messages"][-1].content
\ No newline at end of file
+    return response["messages"][-1].content
+
+# ------------------------------
+
+# line protocol used by the rust narrator (src/narrator.rs):
+# one prompt per line on stdin, tab-separated fields with \\, \n and \t escaped,
+# one escaped response per line on stdout
+
+def _escape(field):
+    return field.replace("\\", "\\\\").replace("\n", "\\n").replace("\t", "\\t")
+
+def _unescape(field):
+    out = []
+    chars = iter(field)
+    for c in chars:
+        if c != "\\":
+            out.append(c)
+            continue
+        n = next(chars, "\\")
+        out.append({"n": "\n", "t": "\t"}.get(n, n))
+    return "".join(out)
+
+def serve():
+    import sys
+    for line in sys.stdin:
+        kind, *args = [_unescape(f) for f in line.rstrip("\n").split("\t")]
+        if kind == "action":
+            actions, question = args
+            response = generateAction(actions.split("\n") if actions else [], question)
+        elif kind == "description":
+            response = generateDescription(args[0])
+        elif kind == "scene":
+            location, description, items, question = args
+            response = generateScene(items or "nothing of note", location, description, question, [])
+        else:
+            response = "None"
+        sys.stdout.write(_escape(response.strip()) + "\n")
+        sys.stdout.flush()
+
+if __name__ == "__main__":
+    import sys
+    if sys.argv[1:] == ["serve"]:
+        serve()
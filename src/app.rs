@@ -1,14 +1,44 @@
+use std::fs;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseEventKind};
-use ratatui::widgets::ListState;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind};
+use ratatui::layout::{Position, Rect};
 
-#[derive(PartialEq, Clone)]
-// determines if a message was sent by the user or the game (theming)
+use wwizard::{engine::{ERROR, NARRATOR, SYSTEM}, Engine, Output};
+
+use crate::clipboard;
+use crate::crash;
+use crate::config::{Config, KeyAction};
+use crate::transcript::{self, Format};
+
+// lines a turn of the mouse wheel scrolls
+const WHEEL_LINES: usize = 3;
+
+// how long to wait for an event before giving main a chance to notice a signal
+const EVENT_POLL: Duration = Duration::from_millis(250);
+
+#[derive(PartialEq, Clone, Debug)]
+// determines if a message was sent by the user or the game (theming).
+// game messages carry the engine's category: narrator, hint, system, error, or whoever is speaking
 pub enum MessageType {
     User,
-    Game,
-    Jesse
+    Game(String)
+}
+
+impl MessageType {
+    pub fn narrator() -> MessageType {
+        MessageType::Game(NARRATOR.to_string())
+    }
+
+    // the name styles are looked up by
+    pub fn category(&self) -> &str {
+        match self {
+            MessageType::User => "user",
+            MessageType::Game(category) => category
+        }
+    }
 }
 
 // stores a message that is to be displayed in the text_area of the ui
@@ -18,12 +48,57 @@ pub struct Message {
     pub msg_type: MessageType
 }
 
+// something on screen that submits a command when clicked, like an exit
+#[derive(Clone, Debug, PartialEq)]
+pub struct Button {
+    pub area: Rect,
+    pub command: String
+}
+
+// what keys do to the message pane
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum Mode {
+    // typing commands
+    Input,
+    // typing a search; matches are highlighted and the current one is selected
+    Search,
+    // moving between messages to copy one
+    Select
+}
+
 // stores the state of the program
 pub struct App {
     pub exit: bool,
 
+    pub engine: Engine,
+
+    // keys and theme for the terminal frontend
+    pub config: Config,
+
     pub message_history: Vec<Message>,
-    pub message_scroll_state: ListState,
+    // the first wrapped line of the message pane in view, or None to keep up with the newest
+    pub message_scroll: Option<usize>,
+    // how many messages there were when the newest were last in view
+    pub messages_seen: usize,
+    // the ui passes these back each frame, so scrolling can stop at the ends
+    pub message_lines: usize,
+    pub message_height: usize,
+    // set when the selection moves, so the ui scrolls to it
+    pub reveal_selected: bool,
+
+    // where the ui last drew what can be clicked, and where the mouse is
+    pub buttons: Vec<Button>,
+    pub input_area: Rect,
+    pub new_messages_area: Option<Rect>,
+    pub hover: Option<Position>,
+
+    pub mode: Mode,
+    // what's being searched for, highlighted until searching or selecting is done
+    pub search: String,
+    // the index in message_history of the selected message, if any
+    pub selected: Option<usize>,
+    // a word about the last thing done to the messages (like copying one), shown until the next key
+    pub status: Option<String>,
 
     pub input_str: String,
     pub input_char_index: usize,
@@ -33,15 +108,38 @@ pub struct App {
 impl App {
 
     // constructor
-    pub fn new() -> App {
-        App {
+    pub fn new(mut engine: Engine) -> App {
+        let outputs = engine.start();
+        App::with_outputs(engine, outputs)
+    }
+
+    // an app for a game that's already going (e.g. one restored from a save),
+    // showing what the engine last said
+    pub fn with_outputs(engine: Engine, outputs: Vec<Output>) -> App {
+        let mut app = App {
             exit: false,
+            engine,
+            config: Config::default(),
             message_history: vec![],
-            message_scroll_state: ListState::default(),
+            message_scroll: None,
+            messages_seen: 0,
+            message_lines: 0,
+            message_height: 0,
+            reveal_selected: false,
+            buttons: vec![],
+            input_area: Rect::default(),
+            new_messages_area: None,
+            hover: None,
+            mode: Mode::Input,
+            search: String::default(),
+            selected: None,
+            status: None,
             input_str: String::default(),
             input_char_index: usize::default(),
             input_width: usize::default()
-        }
+        };
+        app.post_outputs(outputs);
+        app
     }
 
     // ############################################################################################################
@@ -52,79 +150,109 @@ impl App {
     
     // when input box is submitted, clear input and do something with the input_str
     fn submit_input(&mut self, t: MessageType) {
+        let input = std::mem::take(&mut self.input_str);
+        self.reset_cursor();
+        // whatever this leads to is worth seeing
+        self.scroll_to_bottom();
+
+        // only here, not in submit: the other frontends shouldn't write files on the server
+        if let ["export", "transcript", args @ ..] = input.split_whitespace().collect::<Vec<_>>().as_slice() {
+            self.export_transcript(args);
+            return;
+        }
+        self.submit(&input, t);
+    }
+
+    // `export transcript [html|markdown|text] [path]`: write everything in the message
+    // pane to a file. the format defaults to the path's extension, then to text
+    fn export_transcript(&mut self, args: &[&str]) {
+        let (format, path) = match args {
+            [] => (Format::Text, None),
+            [name] => match Format::from_name(name) {
+                Some(format) => (format, None),
+                None => (Format::from_path(name.as_ref()).unwrap_or(Format::Text), Some(PathBuf::from(name))),
+            },
+            [name, path] => match Format::from_name(name) {
+                Some(format) => (format, Some(PathBuf::from(path))),
+                None => return self.post_system(ERROR, format!("Unknown transcript format {}; try html, markdown or text.", name)),
+            },
+            _ => return self.post_system(ERROR, "Usage: export transcript [html|markdown|text] [path]".into()),
+        };
+        let path = path.unwrap_or_else(|| PathBuf::from(format!("transcript.{}", format.extension())));
 
-        if self.input_str == "quit" {
+        match fs::write(&path, transcript::render(&self.message_history, format)) {
+            Ok(())  => self.post_system(SYSTEM, format!("Transcript written to {}.", path.display())),
+            Err(e)  => self.post_system(ERROR, format!("Couldn't write {}: {}", path.display(), e))
+        }
+    }
+
+    // a message from the frontend itself rather than the game
+    fn post_system(&mut self, category: &str, text: String) {
+        self.post_message(Message {
+            text,
+            msg_type: MessageType::Game(category.to_string())
+        });
+    }
+
+    // (also called by the web frontend)
+    // do something with a line of input, posting whatever it leads to
+    pub fn submit(&mut self, input: &str, t: MessageType) {
+
+        if input == "quit" {
             self.exit();
             return;
         }
-        if self.input_str == "exit" {
-            let msg = Message {
-                text: "go away jesse".to_string(),
-                msg_type: MessageType::Jesse
-            };
-            self.post_message(msg);
+        // nothing left to do once the game is over
+        if self.engine.game.ending.is_some() {
+            return;
+        }
+        if input == "regenerate" {
+            let outputs = self.engine.regenerate();
+            self.post_outputs(outputs);
             return;
         }
 
-        // ############################################################################################################
-        // TODO: CHANGE THIS TO INTERFACE WITH OUR AI STUFF; MAYBE DEDICATED RUST MODUlE FOR AI INTERFACING?
-        //       WHATEVER WE DO, THE RESPONSE MESSAGE SHOULD BE PASSED TO post_message();
         let msg = Message {
-            text: self.input_str.clone(),
+            text: input.to_string(),
             msg_type: t,
             
         };
         self.post_message(msg);
-        // ############################################################################################################
-        
-        // reset input box for next input
-        self.input_str.clear();
-        self.reset_cursor();
+
+        let outputs = self.engine.handle(input);
+        self.post_outputs(outputs);
+    }
+
+    // show what the engine said
+    fn post_outputs(&mut self, outputs: Vec<Output>) {
+        for output in outputs {
+            self.post_message(Message {
+                text: output.text().to_string(),
+                msg_type: MessageType::Game(output.category().to_string())
+            });
+        }
     }
 
     // (called by main)
     // update state based on terminal events
     pub fn handle_terminal_events(&mut self) -> io::Result<()> {
+        if !event::poll(EVENT_POLL)? {
+            return Ok(());
+        }
         match event::read()? {
 
             // on key press
-            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                match key_event.code {
-
-                    KeyCode::Up    => self.messages_scroll_up(),
-                    KeyCode::Down  => self.messages_scroll_down(),
-
-                    KeyCode::Char(c)        => {
-                        if self.input_str.len() > self.input_width {
-                            // TODO: we should really permit this. shouldn't be that hard to scroll horizontally with cursor
-                            ()
-                        } else {
-                            self.enter_char(c)
-                        }
-                    },
-                    KeyCode::Left               => self.move_cursor_left(), 
-                    KeyCode::Right              => self.move_cursor_right(), 
-                    KeyCode::Backspace          => self.delete_char(),
-                    KeyCode::Enter              => {
-                        if self.input_str.is_empty() {
-                            ()
-                        } else {
-                            self.submit_input(MessageType::User);
-                        }
-                    },
-
-                    // TO DELETE: TEST CODE ONLY
-                    KeyCode::Delete => self.submit_input(MessageType::Game),
-                    
-                    _ => ()
-                }
-            }
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => self.handle_key(key_event),
 
             // on mouse capture
             Event::Mouse(mouse_event) => {
                 match mouse_event.kind {
-                    MouseEventKind::ScrollUp    => self.messages_scroll_up(),
-                    MouseEventKind::ScrollDown  => self.messages_scroll_down(),
+                    MouseEventKind::ScrollUp    => self.messages_scroll_up(WHEEL_LINES),
+                    MouseEventKind::ScrollDown  => self.messages_scroll_down(WHEEL_LINES),
+                    MouseEventKind::Moved | MouseEventKind::Drag(_) => {
+                        self.hover = Some(Position::new(mouse_event.column, mouse_event.row));
+                    }
+                    MouseEventKind::Down(MouseButton::Left) => self.click(Position::new(mouse_event.column, mouse_event.row)),
                     _ => ()
                 }
             }
@@ -136,21 +264,233 @@ impl App {
         Ok(())
     }
 
+    // what a key press does
+    fn handle_key(&mut self, key_event: KeyEvent) {
+        // bound keys come first, so they can't be typed
+        let is = |action| self.config.keys.matches(action, &key_event);
+        // ctrl+y is a key, not a y to type
+        let typed = !key_event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let mode = self.mode;
+        self.status = None;
+        match key_event.code {
+
+            // quit, keeping the game to resume, same as SIGINT
+            KeyCode::Char('c') if key_event.modifiers == KeyModifiers::CONTROL => crash::interrupt(),
+
+            // any of these leave the ending screen
+            _ if self.engine.game.ending.is_some() && is(KeyAction::LeaveEnding) => self.exit(),
+
+            // searching and selecting messages
+            _ if mode == Mode::Input && self.input_str.is_empty() && is(KeyAction::Search) => self.start_search(),
+            _ if is(KeyAction::Select)                          => self.toggle_select(),
+            _ if mode != Mode::Input && is(KeyAction::Cancel)   => self.stop_searching(),
+            _ if is(KeyAction::NextMatch)                       => self.find_match(true),
+            _ if is(KeyAction::PrevMatch)                       => self.find_match(false),
+            _ if mode == Mode::Search && is(KeyAction::Submit)  => self.find_match(true),
+            _ if mode == Mode::Search && is(KeyAction::DeleteChar) => {
+                self.search.pop();
+                self.find_latest_match();
+            }
+            KeyCode::Char(c) if mode == Mode::Search && typed && !is(KeyAction::Copy) => {
+                self.search.push(c);
+                self.find_latest_match();
+            }
+            _ if is(KeyAction::Copy) || (mode == Mode::Select && is(KeyAction::Submit)) => self.copy_selected(),
+            _ if mode == Mode::Select && is(KeyAction::ScrollUp)   => self.select_older(),
+            _ if mode == Mode::Select && is(KeyAction::ScrollDown) => self.select_newer(),
+
+            _ if is(KeyAction::ScrollUp)     => self.messages_scroll_up(1),
+            _ if is(KeyAction::ScrollDown)   => self.messages_scroll_down(1),
+            _ if is(KeyAction::PageUp)       => self.messages_scroll_up(self.page()),
+            _ if is(KeyAction::PageDown)     => self.messages_scroll_down(self.page()),
+            _ if is(KeyAction::ScrollTop)    => self.scroll_to(0),
+            _ if is(KeyAction::ScrollBottom) => self.scroll_to_bottom(),
+
+            _ if is(KeyAction::CursorLeft)  => self.move_cursor_left(),
+            _ if is(KeyAction::CursorRight) => self.move_cursor_right(),
+            _ if is(KeyAction::DeleteChar)  => self.delete_char(),
+            _ if is(KeyAction::Submit) && !self.input_str.is_empty() => self.submit_input(MessageType::User),
+
+            // TODO: we should really permit typing past the width. shouldn't be that hard to scroll horizontally with cursor
+            KeyCode::Char(c) if typed && self.input_str.len() <= self.input_width => {
+                // typing goes back to the input box
+                self.stop_searching();
+                self.enter_char(c);
+            }
+
+            // TO DELETE: TEST CODE ONLY
+            KeyCode::Delete => self.submit_input(MessageType::narrator()),
+            
+            _ => ()
+        }
+    }
+
+    // what a left click does: press a button, jump to new messages, or move the cursor
+    fn click(&mut self, position: Position) {
+        if self.engine.game.ending.is_some() {
+            return;
+        }
+        if let Some(button) = self.buttons.iter().find(|button| button.area.contains(position)) {
+            // leave anything half typed where it is
+            let command = button.command.clone();
+            self.stop_searching();
+            self.scroll_to_bottom();
+            self.submit(&command, MessageType::User);
+        } else if self.new_messages_area.is_some_and(|area| area.contains(position)) {
+            self.scroll_to_bottom();
+        } else if self.input_area.contains(position) && self.mode == Mode::Input {
+            // inside the border, before the first column of text
+            let column = position.x.saturating_sub(self.input_area.x + 1);
+            self.input_char_index = self.clamp_cursor(column.into());
+        }
+    }
+
     // exit at the end of this loop iteration
     fn exit(&mut self) {
         self.exit = true;
     }
 
     // ===================================================================================
+    // SEARCH AND SELECTION
+    fn start_search(&mut self) {
+        self.mode = Mode::Search;
+        self.search.clear();
+        self.selected = None;
+    }
+
+    // back to typing commands, forgetting the search and selection
+    fn stop_searching(&mut self) {
+        self.mode = Mode::Input;
+        self.search.clear();
+        self.selected = None;
+    }
+
+    // from typing to picking a message (starting with the newest, or the current
+    // match), and back
+    fn toggle_select(&mut self) {
+        self.reveal_selected = true;
+        if self.mode == Mode::Select {
+            self.stop_searching();
+        } else if !self.message_history.is_empty() {
+            self.mode = Mode::Select;
+            self.selected = self.selected.or(Some(self.message_history.len() - 1));
+        }
+    }
+
+    fn select_older(&mut self) {
+        self.reveal_selected = true;
+        self.selected = self.selected.map(|i| i.saturating_sub(1));
+    }
+
+    fn select_newer(&mut self) {
+        self.reveal_selected = true;
+        let last = self.message_history.len().saturating_sub(1);
+        self.selected = self.selected.map(|i| (i + 1).min(last));
+    }
+
+    // does this message contain what's being searched for? ignoring ascii case, so
+    // matches stay at the same byte offsets for highlighting
+    pub fn matches_search(&self, message: &Message) -> bool {
+        !self.search.is_empty() && message.text.to_ascii_lowercase().contains(&self.search.to_ascii_lowercase())
+    }
+
+    // select the newest message matching the search as it's typed
+    fn find_latest_match(&mut self) {
+        self.reveal_selected = true;
+        self.selected = self.message_history.iter().rposition(|message| self.matches_search(message));
+    }
+
+    // select the next match older (or newer) than the selected message
+    fn find_match(&mut self, older: bool) {
+        if self.search.is_empty() {
+            return;
+        }
+        let len = self.message_history.len();
+        let found = match (older, self.selected) {
+            (true, selected) => self.message_history[..selected.unwrap_or(len)].iter().rposition(|message| self.matches_search(message)),
+            (false, Some(selected)) => self.message_history[selected + 1..]
+                .iter()
+                .position(|message| self.matches_search(message))
+                .map(|i| selected + 1 + i),
+            (false, None) => None
+        };
+        match found {
+            Some(i) => {
+                self.selected = Some(i);
+                self.reveal_selected = true;
+            }
+            None    => self.status = Some("no more matches".into())
+        }
+    }
+
+    // how many messages match the search, and which of them is selected (counting from
+    // 1 at the newest, the way searching goes)
+    pub fn match_position(&self) -> (usize, Option<usize>) {
+        let matches: Vec<usize> = (0..self.message_history.len())
+            .filter(|&i| self.matches_search(&self.message_history[i]))
+            .collect();
+        let current = self.selected.and_then(|selected| matches.iter().rev().position(|&i| i == selected)).map(|i| i + 1);
+        (matches.len(), current)
+    }
+
+    // put the selected message on the clipboard
+    fn copy_selected(&mut self) {
+        let Some(message) = self.selected.and_then(|i| self.message_history.get(i)) else {
+            return;
+        };
+        self.status = Some(match clipboard::copy(&message.text) {
+            Ok(())  => "copied".into(),
+            Err(e)  => format!("couldn't copy: {}", e)
+        });
+    }
+
     // ===================================================================================
-    // INPUT BOX LOGIC: TAKEN FROM RATATUI EXAMPLES REPOSITORY
-    fn messages_scroll_down(&mut self) {
-        *self.message_scroll_state.offset_mut() = self.message_scroll_state.offset().saturating_sub(1);
+    // SCROLLING: in wrapped lines, between the oldest message and the newest
+    fn max_scroll(&self) -> usize {
+        self.message_lines.saturating_sub(self.message_height)
+    }
+
+    // the first line in view
+    pub fn scroll_top(&self) -> usize {
+        self.message_scroll.unwrap_or(self.max_scroll()).min(self.max_scroll())
+    }
+
+    // put this line at the top of the pane, as near as it can be. reaching the bottom
+    // goes back to keeping up with new messages
+    pub fn scroll_to(&mut self, line: usize) {
+        if line >= self.max_scroll() {
+            self.scroll_to_bottom();
+        } else {
+            self.message_scroll = Some(line);
+        }
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.message_scroll = None;
+        self.messages_seen = self.message_history.len();
+    }
+
+    // have messages arrived while scrolled back?
+    pub fn has_new_messages(&self) -> bool {
+        self.message_scroll.is_some() && self.message_history.len() > self.messages_seen
+    }
+
+    fn messages_scroll_down(&mut self, lines: usize) {
+        self.scroll_to(self.scroll_top().saturating_add(lines));
     }
 
-    fn messages_scroll_up(&mut self) {
-        *self.message_scroll_state.offset_mut() = self.message_scroll_state.offset().saturating_add(1);
+    fn messages_scroll_up(&mut self, lines: usize) {
+        self.scroll_to(self.scroll_top().saturating_sub(lines));
     }
+
+    // a pane's worth, keeping a line of what was in view
+    fn page(&self) -> usize {
+        self.message_height.saturating_sub(1).max(1)
+    }
+
+    // ===================================================================================
+    // ===================================================================================
+    // INPUT BOX LOGIC: TAKEN FROM RATATUI EXAMPLES REPOSITORY
     fn move_cursor_left(&mut self) {
         let cursor_moved_left = self.input_char_index.saturating_sub(1);
         self.input_char_index = self.clamp_cursor(cursor_moved_left);
@@ -207,3 +547,170 @@ impl App {
 
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use wwizard::cache::DescriptionCache;
+    use wwizard::narrator::ReplayNarrator;
+    use wwizard::parser::LocationId;
+    use wwizard::World;
+
+    fn replay_app(fixture: &str) -> App {
+        App::new(Engine::new(
+            Arc::new(World::load("assets")),
+            Box::new(ReplayNarrator::load(fixture).unwrap()),
+            DescriptionCache::default(),
+        ))
+    }
+
+    fn submit(app: &mut App, input: &str) {
+        app.input_str = input.into();
+        app.submit_input(MessageType::User);
+    }
+
+    #[test]
+    fn test_replayed_session() {
+        let mut app = replay_app("fixtures/bedroom_session.yaml");
+        assert!(app.message_history[0].text.starts_with("Soft pastel walls"));
+
+        submit(&mut app, "skim through my mail");
+        assert_eq!(app.engine.game.properties.get("didReadLetter"), Some(&true));
+        assert_eq!(app.engine.game.location, LocationId("bedroom".into()));
+
+        submit(&mut app, "go east");
+        assert_eq!(app.engine.game.location, LocationId("hallway".into()));
+
+        let n = app.message_history.len();
+        assert_eq!(app.message_history[n - 1].text, "Exits: west.");
+        assert_eq!(app.message_history[n - 2].text, "Marigold is here.");
+        assert!(app.message_history[n - 3].text.contains("waiting for you"));
+        let description = &app.message_history[n - 4];
+        assert!(description.msg_type == MessageType::narrator());
+        assert_eq!(description.text, "A long, narrow hallway stretches before you, its floorboards creaking companionably underfoot. Nothing much happens here, and the hallway seems rather proud of that.");
+        assert!(app.input_str.is_empty());
+    }
+
+    #[test]
+    fn test_frontend_commands() {
+        let mut app = replay_app("fixtures/bedroom_session.yaml");
+
+        // an aside from the world's jesse voice
+        submit(&mut app, "exit");
+        let last = app.message_history.last().unwrap();
+        assert_eq!(last.text, "go away jesse");
+        assert!(last.msg_type == MessageType::Game("jesse".into()));
+        assert!(!app.exit);
+
+        submit(&mut app, "quit");
+        assert!(app.exit);
+    }
+
+    #[test]
+    fn test_scrolling() {
+        let mut app = replay_app("fixtures/bedroom_session.yaml");
+        // as the ui would say: 30 wrapped lines in a 10 line pane
+        app.message_lines = 30;
+        app.message_height = 10;
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::from(code));
+
+        assert_eq!(app.scroll_top(), 20);
+        press(&mut app, KeyCode::Down);
+        assert_eq!((app.scroll_top(), app.message_scroll), (20, None));
+        press(&mut app, KeyCode::PageUp);
+        assert_eq!(app.scroll_top(), 11);
+        press(&mut app, KeyCode::Home);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.message_scroll, Some(0));
+
+        // scrolled back, new messages wait below until the view returns to them
+        app.submit("wait", MessageType::User);
+        assert!(app.has_new_messages());
+        assert_eq!(app.scroll_top(), 0);
+        press(&mut app, KeyCode::End);
+        assert!(!app.has_new_messages());
+        assert_eq!(app.message_scroll, None);
+    }
+
+    #[test]
+    fn test_clicking() {
+        let mut app = replay_app("fixtures/bedroom_session.yaml");
+        let click = |app: &mut App, column, row| {
+            app.click(Position::new(column, row));
+        };
+        // as the ui would lay them out
+        app.buttons = vec![Button { area: Rect::new(0, 20, 9, 1), command: "go east".into() }];
+        app.input_area = Rect::new(0, 21, 40, 3);
+        app.input_str = "look".into();
+
+        // clicking in the input box moves the cursor, no further than the text
+        click(&mut app, 3, 22);
+        assert_eq!(app.input_char_index, 2);
+        click(&mut app, 30, 22);
+        assert_eq!(app.input_char_index, 4);
+
+        // clicking a button submits it, leaving what was typed alone
+        click(&mut app, 4, 20);
+        assert_eq!(app.engine.game.location, LocationId("hallway".into()));
+        assert!(app.message_history.iter().any(|message| message.text == "go east" && message.msg_type == MessageType::User));
+        assert_eq!(app.input_str, "look");
+    }
+
+    #[test]
+    fn test_search_and_export() {
+        let mut app = replay_app("fixtures/bedroom_session.yaml");
+        app.input_width = 80;
+        submit(&mut app, "go east");
+        let press = |app: &mut App, code| app.handle_key(KeyEvent::from(code));
+
+        // searching picks the newest match first, then older ones
+        press(&mut app, KeyCode::Char('/'));
+        for c in "EXITS".chars() {
+            press(&mut app, KeyCode::Char(c));
+        }
+        assert_eq!(app.mode, Mode::Search);
+        assert_eq!(app.match_position().1, Some(1));
+        let newest = app.selected.unwrap();
+        assert_eq!(app.message_history[newest].text, "Exits: west.");
+        press(&mut app, KeyCode::Enter);
+        assert!(app.selected.unwrap() < newest);
+        assert_eq!(app.match_position().1, Some(2));
+
+        // selecting carries on from the match, and escape forgets both
+        press(&mut app, KeyCode::Tab);
+        assert_eq!(app.mode, Mode::Select);
+        let selected = app.selected.unwrap();
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.selected, Some(selected.saturating_sub(1)));
+        press(&mut app, KeyCode::Esc);
+        assert_eq!((app.mode, app.selected, app.search.as_str()), (Mode::Input, None, ""));
+
+        // typing / mid-command is just a slash
+        press(&mut app, KeyCode::Char('a'));
+        press(&mut app, KeyCode::Char('/'));
+        assert_eq!((app.mode, app.input_str.as_str()), (Mode::Input, "a/"));
+
+        // held with ctrl or alt, a letter is a key rather than something to type
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT));
+        app.handle_key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::CONTROL));
+        assert_eq!(app.input_str, "a/");
+
+        let path = std::env::temp_dir().join(format!("wwizard-transcript-{}.md", std::process::id()));
+        submit(&mut app, &format!("export transcript {}", path.display()));
+        let markdown = std::fs::read_to_string(&path).unwrap();
+        assert!(markdown.contains("**\\> go east**"));
+        assert!(app.message_history.last().unwrap().msg_type == MessageType::Game(SYSTEM.into()));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_ctrl_c() {
+        let mut app = replay_app("fixtures/bedroom_session.yaml");
+        app.input_width = 80;
+
+        // ends the game loop like SIGINT, so the game is saved on the way out
+        app.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert!(crash::interrupted());
+        assert!(app.input_str.is_empty());
+    }
+}
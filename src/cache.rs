@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+
+use yaml_rust2::{yaml::Hash, Yaml, YamlEmitter, YamlLoader};
+
+use crate::game::Game;
+use crate::narrator::Prompt;
+use crate::parser::Location;
+
+// where embellished descriptions are kept between sessions, unless WWIZARD_CACHE says otherwise
+const DEFAULT_CACHE_PATH: &str = ".wwizard-cache.yaml";
+
+// remembers embellished location descriptions so revisiting a room doesn't cost
+// another AI call (and doesn't reword the room every time).
+//
+// entries are keyed by location id, the hash of the raw description and the values
+// of every property the location's actions depend on, so editing the YAML or changing
+// the room's state gets a fresh embellishment
+#[derive(Default)]
+pub struct DescriptionCache {
+    // None for a cache that only lives as long as the program
+    path: Option<PathBuf>,
+    entries: HashMap<String, String>,
+}
+
+impl DescriptionCache {
+    // persistent cache at WWIZARD_CACHE, or DEFAULT_CACHE_PATH.
+    // WWIZARD_CACHE=off disables persistence
+    pub fn from_env() -> io::Result<DescriptionCache> {
+        match env::var("WWIZARD_CACHE") {
+            Ok(setting) if setting == "off" => Ok(DescriptionCache::default()),
+            Ok(path) => DescriptionCache::open(path),
+            Err(_) => DescriptionCache::open(DEFAULT_CACHE_PATH),
+        }
+    }
+
+    pub fn open(path: impl Into<PathBuf>) -> io::Result<DescriptionCache> {
+        let path = path.into();
+        let mut entries = HashMap::new();
+
+        if path.exists() {
+            let source = fs::read_to_string(&path)?;
+            let docs = YamlLoader::load_from_str(&source)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+            // the file can be edited by hand, so a bad one is an error rather than a panic
+            let invalid = |message: &str| io::Error::new(ErrorKind::InvalidData, message);
+            if let Some(doc) = docs.first() {
+                let Yaml::Hash(mapping) = doc else {
+                    return Err(invalid("description cache should be a YAML mapping"));
+                };
+                for (key, text) in mapping {
+                    let (Yaml::String(key), Yaml::String(text)) = (key, text) else {
+                        return Err(invalid(
+                            "description cache keys and descriptions should be strings",
+                        ));
+                    };
+                    entries.insert(key.clone(), text.clone());
+                }
+            }
+        }
+
+        Ok(DescriptionCache {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    pub fn get(&self, location: &Location, game: &Game) -> Option<&String> {
+        self.entries.get(&cache_key(location, game))
+    }
+
+    pub fn insert(&mut self, location: &Location, game: &Game, text: String) -> io::Result<()> {
+        self.entries.insert(cache_key(location, game), text);
+        self.save()
+    }
+
+    // forget the current embellishment so the next description is generated fresh
+    pub fn invalidate(&mut self, location: &Location, game: &Game) -> io::Result<()> {
+        self.entries.remove(&cache_key(location, game));
+        self.save()
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        // sorted so the file diffs nicely
+        let mut keys: Vec<&String> = self.entries.keys().collect();
+        keys.sort();
+        let mut mapping = Hash::new();
+        for key in keys {
+            mapping.insert(
+                Yaml::String(key.clone()),
+                Yaml::String(self.entries[key].clone()),
+            );
+        }
+
+        let mut out = String::new();
+        YamlEmitter::new(&mut out)
+            .dump(&Yaml::Hash(mapping))
+            .map_err(|e| io::Error::other(format!("{:?}", e)))?;
+        out.push('\n');
+        fs::write(path, out)
+    }
+}
+
+// <location>:<description hash>:<prop>=<value>,...
+fn cache_key(location: &Location, game: &Game) -> String {
+    let description_hash = Prompt::Description {
+        description: location.description.clone(),
+    }
+    .hash();
+
+    let mut properties: Vec<&str> = location
+        .actions
+        .iter()
+        .flat_map(|action| action.condition.properties())
+        .collect();
+    properties.sort();
+    properties.dedup();
+
+    let state: Vec<String> = properties
+        .into_iter()
+        .map(|property| {
+            let value = game.properties.get(property).unwrap_or(&false);
+            format!("{}={}", property, value)
+        })
+        .collect();
+
+    format!("{}:{}:{}", location.title, description_hash, state.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{load_locations, LocationId};
+
+    #[test]
+    fn test_cache_key_tracks_relevant_state() {
+        let locations = load_locations("assets");
+        let hallway = locations.iter().find(|l| l.title == "hallway").unwrap();
+        let mut game = Game::new(LocationId("hallway".into()));
+
+        let mut cache = DescriptionCache::default();
+        cache.insert(hallway, &game, "a cosy room".into()).unwrap();
+        assert_eq!(cache.get(hallway, &game).unwrap(), "a cosy room");
+
+        // irrelevant properties don't matter
+        game.properties.insert("somethingElse".into(), true);
+        assert!(cache.get(hallway, &game).is_some());
+
+        // ones the hallway's actions check do
+        game.properties.insert("didReadLetter".into(), true);
+        assert!(cache.get(hallway, &game).is_none());
+    }
+
+    #[test]
+    fn test_malformed_cache() {
+        let path = env::temp_dir().join(format!("wwizard-cache-{}.yaml", std::process::id()));
+        for source in [
+            "- a list\n",
+            "hallway: [not, text]\n",
+            "hallway: \"unclosed\n",
+        ] {
+            fs::write(&path, source).unwrap();
+            assert_eq!(
+                DescriptionCache::open(&path).err().unwrap().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
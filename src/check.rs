@@ -0,0 +1,1450 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::{yaml::Hash, Yaml};
+
+use crate::parser::{
+    parse_condition, parse_directive_str, yaml_files, Direction, Directive, DIRECTION_WORDS,
+    PLAYER_SCOPE, PROPERTY_CHARS,
+};
+use crate::verbs::inventory_name;
+
+// ===================================================================================
+// DIAGNOSTICS
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// one problem in a world file, printed like a compiler message:
+//      assets/hallway.yaml:9:5: warning: there is no location called hell
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    pub line: usize,
+    pub col: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {}: {}",
+            self.file.display(),
+            self.line,
+            self.col,
+            severity,
+            self.message
+        )
+    }
+}
+
+// the kinds of file a world is made of
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WorldFile {
+    Location,
+    Npc,
+    Events,
+    Voice,
+}
+
+impl WorldFile {
+    pub const ALL: [WorldFile; 4] = [
+        WorldFile::Location,
+        WorldFile::Npc,
+        WorldFile::Events,
+        WorldFile::Voice,
+    ];
+
+    pub fn from_name(name: &str) -> Option<WorldFile> {
+        WorldFile::ALL.into_iter().find(|file| file.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorldFile::Location => "location",
+            WorldFile::Npc => "npc",
+            WorldFile::Events => "events",
+            WorldFile::Voice => "voice",
+        }
+    }
+
+    fn about(&self) -> &'static str {
+        match self {
+            WorldFile::Location => {
+                "One location of a wwizard world. The file name is the location id."
+            }
+            WorldFile::Npc => "A character to talk to, in the world's npcs directory.",
+            WorldFile::Events => {
+                "Things that happen by themselves, in the world's events directory."
+            }
+            WorldFile::Voice => "How messages in one voice look, in the world's voices directory.",
+        }
+    }
+
+    fn rule(&self) -> &'static Rule {
+        match self {
+            WorldFile::Location => &LOCATION,
+            WorldFile::Npc => &NPC,
+            WorldFile::Events => &EVENTS,
+            WorldFile::Voice => &VOICE,
+        }
+    }
+
+    // where this kind of file lives, inside the world directory
+    fn dir(&self, world: &Path) -> PathBuf {
+        match self {
+            WorldFile::Location => world.to_path_buf(),
+            WorldFile::Npc => world.join("npcs"),
+            WorldFile::Events => world.join("events"),
+            WorldFile::Voice => world.join("voices"),
+        }
+    }
+}
+
+// check every file under dir, the way the game would load them. unlike the loaders,
+// this keeps going after the first problem and says where each one is
+pub fn check_world(dir: &str) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+    let dir = Path::new(dir);
+
+    for file in WorldFile::ALL {
+        let mut paths = yaml_files(file.dir(dir));
+        paths.sort();
+        for path in &paths {
+            checker.check_file(path, file);
+            if file == WorldFile::Location {
+                if let Some(stem) = path.file_stem() {
+                    checker.locations.insert(stem.to_string_lossy().into());
+                }
+            }
+        }
+    }
+
+    checker.check_references();
+    checker.diagnostics
+}
+
+// ===================================================================================
+// MARKED YAML
+//
+// yaml_rust2's Yaml values forget where they came from, so the checker builds its own
+// tree from the parser's events, keeping the marker of each node
+
+struct Node {
+    value: Value,
+    mark: Marker,
+}
+
+enum Value {
+    // resolved the same way the loaders see it, so `title: 5` is a number, not a string
+    Scalar(Yaml),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+impl Node {
+    fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Value::Scalar(Yaml::String(s)) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        match &self.value {
+            Value::Scalar(Yaml::Integer(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        match &self.value {
+            Value::Mapping(pairs) => pairs
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(key))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+// a sequence or mapping that hasn't ended yet. a mapping's items alternate key, value
+struct Frame {
+    mark: Marker,
+    is_mapping: bool,
+    items: Vec<Node>,
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    stack: Vec<Frame>,
+    root: Option<Node>,
+}
+
+impl TreeBuilder {
+    fn push(&mut self, node: Node) {
+        match self.stack.last_mut() {
+            Some(frame) => frame.items.push(node),
+            // only the first document counts, same as the loaders
+            None => {
+                self.root.get_or_insert(node);
+            }
+        }
+    }
+}
+
+impl MarkedEventReceiver for TreeBuilder {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            // quoted scalars are always strings. tags, like anchors, aren't used by
+            // any world, so a tagged scalar is left as a string too
+            Event::Scalar(s, style, _, tag) => self.push(Node {
+                value: Value::Scalar(if style == TScalarStyle::Plain && tag.is_none() {
+                    Yaml::from_str(&s)
+                } else {
+                    Yaml::String(s)
+                }),
+                mark,
+            }),
+            // an alias checks as an empty string
+            Event::Alias(_) => self.push(Node {
+                value: Value::Scalar(Yaml::String(String::new())),
+                mark,
+            }),
+            Event::SequenceStart(..) | Event::MappingStart(..) => self.stack.push(Frame {
+                mark,
+                is_mapping: matches!(event, Event::MappingStart(..)),
+                items: vec![],
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let frame = self
+                    .stack
+                    .pop()
+                    .expect("Collection end should match a start.");
+                let value = if frame.is_mapping {
+                    let mut items = frame.items.into_iter();
+                    let mut pairs = vec![];
+                    while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                        pairs.push((key, value));
+                    }
+                    Value::Mapping(pairs)
+                } else {
+                    Value::Sequence(frame.items)
+                };
+                self.push(Node {
+                    value,
+                    mark: frame.mark,
+                });
+            }
+            _ => (),
+        }
+    }
+}
+
+// ===================================================================================
+// RULES
+//
+// what each kind of world file may contain, as a table. the checker walks files with
+// these rules and `wwizard schema` turns the same ones into json schemas, so an editor
+// and `wwizard check` agree on what's allowed. the loaders in parser.rs are the final
+// word; anything they accept or reject should be written down here too
+
+enum Rule {
+    Text,
+    Flag,
+    // a number of turns, 0 or more
+    Turns,
+    // a random branch's weight, 1 or more
+    Weight,
+    Direction,
+    Condition,
+    Directives,
+    // a location id, checked once every location file has been read
+    Location,
+    // either form of action; see Checker::action
+    Action,
+    List {
+        item: &'static str,
+        rule: &'static Rule,
+    },
+    // string keys of any name, all with the same kind of value
+    Map {
+        key: &'static str,
+        value: &'static str,
+        rule: &'static Rule,
+    },
+    // a mapping with exactly one pair, like `- east: hallway`
+    Pair {
+        name: &'static str,
+        key: &'static str,
+        key_rule: &'static Rule,
+        value: &'static str,
+        value_rule: &'static Rule,
+    },
+    // a mapping with these keys and no others
+    Fields(&'static str, &'static [Field]),
+    // a scalar in the short form, otherwise the long form
+    Either {
+        short: &'static Rule,
+        long: &'static Rule,
+    },
+}
+
+struct Field {
+    key: &'static str,
+    rule: Rule,
+    required: bool,
+    about: &'static str,
+}
+
+const fn field(key: &'static str, rule: Rule, about: &'static str) -> Field {
+    Field {
+        key,
+        rule,
+        required: false,
+        about,
+    }
+}
+
+const fn required(key: &'static str, rule: Rule, about: &'static str) -> Field {
+    Field {
+        key,
+        rule,
+        required: true,
+        about,
+    }
+}
+
+const HOOK: Rule = Rule::Fields(
+    "hook",
+    &[
+        field("text", Rule::Text, "Told to the player."),
+        field("do", Rule::Directives, ""),
+    ],
+);
+
+const LOCATION: Rule = Rule::Fields(
+    "location",
+    &[
+        required(
+            "description",
+            Rule::Text,
+            "What the player sees on arriving. The narrator embellishes it.",
+        ),
+        field(
+            "actions",
+            Rule::List {
+                item: "action",
+                rule: &Rule::Action,
+            },
+            "Things the player can do here.",
+        ),
+        field(
+            "exits",
+            Rule::List {
+                item: "exit",
+                rule: &EXIT,
+            },
+            "Ways out of this location.",
+        ),
+        field(
+            "objects",
+            Rule::Map {
+                key: "object name",
+                value: "object description",
+                rule: &Rule::Text,
+            },
+            "Scenery the player can examine, by name.",
+        ),
+        field("on_enter", HOOK, "Runs every time the player arrives."),
+        field("on_exit", HOOK, "Runs every time the player leaves."),
+        field(
+            "first_visit",
+            HOOK,
+            "Runs the first time the player arrives.",
+        ),
+    ],
+);
+
+//      - east: hallway
+//      - north:
+//          to: attic
+//          when: hasLadder
+//          locked: The trapdoor is too high to reach.
+const EXIT: Rule = Rule::Pair {
+    name: "exit",
+    key: "direction",
+    key_rule: &Rule::Direction,
+    value: "target",
+    value_rule: &Rule::Either {
+        short: &Rule::Location,
+        long: &Rule::Fields(
+            "exit",
+            &[
+                required("to", Rule::Location, "The location this exit leads to."),
+                field(
+                    "when",
+                    Rule::Condition,
+                    "The exit is locked while this is false.",
+                ),
+                field(
+                    "locked",
+                    Rule::Text,
+                    "Told to the player when the exit is locked.",
+                ),
+            ],
+        ),
+    },
+};
+
+// the compact form is checked by hand, in Checker::action
+const EXPLICIT_ACTION: Rule = Rule::Fields(
+    "action",
+    &[
+        required("title", Rule::Text, "What the player types to do this."),
+        field(
+            "aliases",
+            Rule::List {
+                item: "action alias",
+                rule: &Rule::Text,
+            },
+            "Other ways of saying the title.",
+        ),
+        field(
+            "when",
+            Rule::Condition,
+            "The action only succeeds while this is true.",
+        ),
+        field("do", Rule::Directives, ""),
+        field(
+            "else",
+            Rule::Directives,
+            "Applied instead of do when the condition is false.",
+        ),
+        field(
+            "fail",
+            Rule::Text,
+            "Told to the player when the condition is false.",
+        ),
+        field(
+            "hidden",
+            Rule::Flag,
+            "Left out of help and the narrator's list.",
+        ),
+        field("once", Rule::Flag, "Can only be done successfully once."),
+    ],
+);
+
+//      - random:
+//        - 3:
+//          - say "Heads."
+const RANDOM: Rule = Rule::List {
+    item: "random branch",
+    rule: &Rule::Pair {
+        name: "random branch",
+        key: "weight",
+        key_rule: &Rule::Weight,
+        value: "directives",
+        value_rule: &Rule::Directives,
+    },
+};
+
+const VOICE: Rule = Rule::Fields(
+    "voice",
+    &[
+        required(
+            "name",
+            Rule::Text,
+            "The category of message this styles: an npc's name, narrator, hint, system, error or any other.",
+        ),
+        field(
+            "style",
+            Rule::Text,
+            "How frontends draw it, e.g. bold lightmagenta or black on yellow.",
+        ),
+        field(
+            "asides",
+            Rule::Map {
+                key: "aside input",
+                value: "aside",
+                rule: &Rule::Text,
+            },
+            "Exact inputs, and a reply in this voice. Asides don't take a turn.",
+        ),
+    ],
+);
+
+const NPC: Rule = Rule::Fields(
+    "npc",
+    &[
+        required("name", Rule::Text, ""),
+        required("location", Rule::Location, "Where the npc can be found."),
+        required(
+            "description",
+            Rule::Text,
+            "What the player sees when examining the npc.",
+        ),
+        required(
+            "dialogue",
+            Rule::Map {
+                key: "dialogue node id",
+                value: "dialogue node",
+                rule: &DIALOGUE_NODE,
+            },
+            "The conversation, by node id. It begins at start.",
+        ),
+    ],
+);
+
+const DIALOGUE_NODE: Rule = Rule::Fields(
+    "dialogue node",
+    &[
+        required("text", Rule::Text, "What the npc says."),
+        field(
+            "options",
+            Rule::List {
+                item: "dialogue option",
+                rule: &Rule::Either {
+                    short: &Rule::Text,
+                    long: &Rule::Pair {
+                        name: "dialogue option",
+                        key: "text",
+                        key_rule: &Rule::Text,
+                        value: "details",
+                        value_rule: &DIALOGUE_OPTION,
+                    },
+                },
+            },
+            "What the player can say back.",
+        ),
+    ],
+);
+
+const DIALOGUE_OPTION: Rule = Rule::Fields(
+    "dialogue option",
+    &[
+        field(
+            "when",
+            Rule::Condition,
+            "The option is only offered while this is true.",
+        ),
+        field("do", Rule::Directives, ""),
+        field(
+            "goto",
+            Rule::Text,
+            "The node the conversation carries on from. Without one, it ends.",
+        ),
+    ],
+);
+
+const EVENTS: Rule = Rule::List {
+    item: "event",
+    rule: &Rule::Pair {
+        name: "event",
+        key: "id",
+        key_rule: &Rule::Text,
+        value: "details",
+        value_rule: &Rule::Fields(
+            "event",
+            &[
+                field("when", Rule::Condition, "Happens once this is true."),
+                field(
+                    "enter",
+                    Rule::Location,
+                    "Happens when the player arrives here.",
+                ),
+                field(
+                    "after",
+                    Rule::Turns,
+                    "How many turns after its trigger it happens.",
+                ),
+                field("text", Rule::Text, "Told to the player."),
+                field("do", Rule::Directives, ""),
+                field(
+                    "repeat",
+                    Rule::Flag,
+                    "Happens every time it's triggered, not just once.",
+                ),
+            ],
+        ),
+    },
+};
+
+// ===================================================================================
+// CHECKS
+
+#[derive(Default)]
+struct Checker {
+    file: PathBuf,
+    diagnostics: Vec<Diagnostic>,
+    // names of the location files seen so far
+    locations: HashSet<String>,
+    // every location id mentioned anywhere, and where, to check once all files are read
+    references: Vec<(PathBuf, Marker, String)>,
+}
+
+impl Checker {
+    fn report(&mut self, severity: Severity, line: usize, col: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            file: self.file.clone(),
+            line,
+            col,
+            severity,
+            message,
+        });
+    }
+
+    fn report_at(&mut self, severity: Severity, mark: Marker, message: String) {
+        self.report(severity, mark.line(), mark.col() + 1, message);
+    }
+
+    fn error(&mut self, node: &Node, message: impl Into<String>) {
+        self.report_at(Severity::Error, node.mark, message.into());
+    }
+
+    fn check_file(&mut self, path: &Path, file: WorldFile) {
+        self.file = path.to_path_buf();
+        match fs::read_to_string(path) {
+            Ok(source) => self.check_source(&source, file),
+            Err(e) => self.report(Severity::Error, 1, 1, format!("couldn't read file: {}", e)),
+        }
+    }
+
+    fn check_source(&mut self, source: &str, file: WorldFile) {
+        let mut builder = TreeBuilder::default();
+        if let Err(e) = Parser::new_from_str(source).load(&mut builder, false) {
+            self.report_at(Severity::Error, *e.marker(), e.info().into());
+            return;
+        }
+        let Some(root) = builder.root else {
+            self.report(Severity::Error, 1, 1, "file is empty".into());
+            return;
+        };
+        self.rule(&root, file.rule(), file.name());
+        // what a table can't say: how parts of a file refer to each other
+        match file {
+            WorldFile::Npc => self.dialogue_targets(&root),
+            WorldFile::Events => self.event_triggers(&root),
+            WorldFile::Location | WorldFile::Voice => (),
+        }
+    }
+
+    fn check_references(&mut self) {
+        for (file, mark, target) in std::mem::take(&mut self.references) {
+            if !self.locations.contains(&target) {
+                self.file = file;
+                self.report_at(
+                    Severity::Warning,
+                    mark,
+                    format!("there is no location called {}", target),
+                );
+            }
+        }
+    }
+
+    fn reference(&mut self, node: &Node, target: &str) {
+        self.references
+            .push((self.file.clone(), node.mark, target.to_string()));
+    }
+
+    fn rule(&mut self, node: &Node, rule: &Rule, what: &str) {
+        match rule {
+            Rule::Text => {
+                self.string(node, what);
+            }
+            Rule::Flag => {
+                if !matches!(node.value, Value::Scalar(Yaml::Boolean(_))) {
+                    self.error(node, format!("{} should be true or false", what));
+                }
+            }
+            Rule::Turns => {
+                if node
+                    .as_integer()
+                    .and_then(|n| u32::try_from(n).ok())
+                    .is_none()
+                {
+                    self.error(node, format!("{} should be a number of turns", what));
+                }
+            }
+            Rule::Weight => {
+                if node
+                    .as_integer()
+                    .and_then(|n| u32::try_from(n).ok())
+                    .is_none_or(|weight| weight == 0)
+                {
+                    self.error(
+                        node,
+                        format!("{} should be a whole number from 1 to {}", what, u32::MAX),
+                    );
+                }
+            }
+            Rule::Direction => {
+                if let Some(word) = self.string(node, what) {
+                    if Direction::from_word(word).is_none() {
+                        self.error(
+                            node,
+                            format!(
+                                "{} {} should be a compass direction, up, down, in or out",
+                                what, word
+                            ),
+                        );
+                    }
+                }
+            }
+            Rule::Condition => self.condition(node),
+            Rule::Directives => self.directives(node),
+            Rule::Location => {
+                if let Some(location_id) = self.string(node, what) {
+                    self.reference(node, location_id);
+                }
+            }
+            Rule::Action => self.action(node),
+            Rule::List { item, rule } => {
+                for child in self.sequence(node, what) {
+                    self.rule(child, rule, item);
+                }
+            }
+            Rule::Map { key, value, rule } => {
+                for (child_key, child_value) in self.mapping(node, what) {
+                    self.string(child_key, key);
+                    self.rule(child_value, rule, value);
+                }
+            }
+            Rule::Pair {
+                name,
+                key,
+                key_rule,
+                value,
+                value_rule,
+            } => {
+                if let Some((pair_key, pair_value)) = self.single_pair(node, name) {
+                    self.rule(pair_key, key_rule, &format!("{} {}", name, key));
+                    self.rule(pair_value, value_rule, &format!("{} {}", name, value));
+                }
+            }
+            Rule::Fields(name, fields) => self.fields(node, name, fields),
+            Rule::Either { short, long } => match node.value {
+                Value::Scalar(_) => self.rule(node, short, what),
+                _ => self.rule(node, long, what),
+            },
+        }
+    }
+
+    // ---------------------------------------------------------------------------
+    // building blocks
+
+    fn string<'a>(&mut self, node: &'a Node, what: &str) -> Option<&'a str> {
+        let s = node.as_str();
+        if s.is_none() {
+            self.error(node, format!("{} should be a string", what));
+        }
+        s
+    }
+
+    fn sequence<'a>(&mut self, node: &'a Node, what: &str) -> &'a [Node] {
+        match &node.value {
+            Value::Sequence(items) => items,
+            _ => {
+                self.error(node, format!("{} should be a list", what));
+                &[]
+            }
+        }
+    }
+
+    fn mapping<'a>(&mut self, node: &'a Node, what: &str) -> &'a [(Node, Node)] {
+        match &node.value {
+            Value::Mapping(pairs) => pairs,
+            _ => {
+                self.error(node, format!("{} should be a mapping", what));
+                &[]
+            }
+        }
+    }
+
+    // a mapping with exactly one key, like `- read letter: ...`
+    fn single_pair<'a>(&mut self, node: &'a Node, what: &str) -> Option<&'a (Node, Node)> {
+        let pairs = self.mapping(node, what);
+        if pairs.len() != 1 && !pairs.is_empty() {
+            self.error(&pairs[1].0, format!("{} should have a single key", what));
+        }
+        pairs.first()
+    }
+
+    fn fields(&mut self, node: &Node, name: &str, fields: &[Field]) {
+        for (key, value) in self.mapping(node, name) {
+            match fields.iter().find(|field| key.as_str() == Some(field.key)) {
+                Some(field) => self.rule(value, &field.rule, &format!("{} {}", name, field.key)),
+                None => self.error(
+                    key,
+                    format!(
+                        "unknown {} key {}; expected one of {}",
+                        name,
+                        key.as_str().unwrap_or("?"),
+                        fields
+                            .iter()
+                            .map(|field| field.key)
+                            .collect::<Vec<&str>>()
+                            .join(", ")
+                    ),
+                ),
+            }
+        }
+        if let Value::Mapping(_) = node.value {
+            for field in fields.iter().filter(|field| field.required) {
+                if node.get(field.key).is_none() {
+                    self.error(node, format!("{} should have a {}", name, field.key));
+                }
+            }
+        }
+    }
+
+    fn condition(&mut self, node: &Node) {
+        let Some(s) = self.string(node, "condition") else {
+            return;
+        };
+        if parse_condition(s).is_err() {
+            self.error(
+                node,
+                format!(
+                    "condition {} should be always, a property id, or ! and a condition",
+                    s
+                ),
+            );
+        }
+    }
+
+    fn directives(&mut self, node: &Node) {
+        for directive in self.sequence(node, "directives") {
+            self.directive(directive);
+        }
+    }
+
+    fn directive(&mut self, node: &Node) {
+        if let Value::Mapping(_) = node.value {
+            let Some((key, branches)) = self.single_pair(node, "directive mapping") else {
+                return;
+            };
+            if key.as_str() != Some("random") {
+                self.error(key, "directive mapping should be a random directive");
+                return;
+            }
+            if matches!(&branches.value, Value::Sequence(items) if items.is_empty()) {
+                self.error(branches, "random directive should have at least one branch");
+            }
+            self.rule(branches, &RANDOM, "random directive");
+            return;
+        }
+        let Some(s) = self.string(node, "directive") else {
+            return;
+        };
+        match parse_directive_str(s) {
+            Ok(Directive::GoTo(location)) => self.reference(node, &location.0),
+            Ok(Directive::SetProperty(property, _) | Directive::Toggle(property)) => {
+                self.player_property(node, &property.0)
+            }
+            Ok(_) => (),
+            Err(message) => self.error(node, message),
+        }
+    }
+
+    // something the player carries, but that every player shares in a shared game
+    fn player_property(&mut self, node: &Node, property: &str) {
+        if inventory_name(property).is_some() && !property.starts_with(PLAYER_SCOPE) {
+            self.report_at(
+                Severity::Warning,
+                node.mark,
+                format!(
+                    "{} is shared by every player in serve --shared; write {}{} to give each player their own",
+                    property, PLAYER_SCOPE, property
+                ),
+            );
+        }
+    }
+
+    // ---------------------------------------------------------------------------
+    // actions
+    //
+    // compact, which the rule table can't describe, since its one key is a condition:
+    //      - unlock door:
+    //          hasKey:
+    //          - set isDoorOpen true
+    //          fail: The door is locked.
+    // or explicit, with a title key. a title key on its own, holding conditions, is a
+    // compact action for typing "title"
+
+    fn action(&mut self, node: &Node) {
+        let compact_title = matches!(&node.value, Value::Mapping(pairs) if pairs.len() == 1)
+            && node
+                .get("title")
+                .is_some_and(|title| matches!(title.value, Value::Mapping(_)));
+        if node.get("title").is_some() && !compact_title {
+            self.rule(node, &EXPLICIT_ACTION, "action");
+            return;
+        }
+
+        let Some((title, details)) = self.single_pair(node, "compact action") else {
+            return;
+        };
+        self.string(title, "action title");
+        let pairs = self.mapping(details, "action details");
+        let Some((condition, directives)) = pairs.first() else {
+            self.error(details, "action should have a condition and directives");
+            return;
+        };
+        self.condition(condition);
+        self.directives(directives);
+        for (key, value) in &pairs[1..] {
+            match key.as_str() {
+                Some("else") => self.directives(value),
+                Some("fail") => {
+                    self.string(value, "action fail message");
+                }
+                _ => self.error(
+                    key,
+                    "action should have a single condition; split it into separate actions or use the explicit form",
+                ),
+            }
+        }
+    }
+
+    // ---------------------------------------------------------------------------
+    // references inside a file
+
+    // every npc needs a start node, and every goto a node to go to
+    fn dialogue_targets(&mut self, root: &Node) {
+        let Some(Value::Mapping(nodes)) = root.get("dialogue").map(|dialogue| &dialogue.value)
+        else {
+            return;
+        };
+        let ids: Vec<&str> = nodes.iter().filter_map(|(id, _)| id.as_str()).collect();
+        if !nodes.is_empty() && !ids.contains(&"start") {
+            self.error(&nodes[0].0, "npc dialogue should have a start node");
+        }
+        for (_, node) in nodes {
+            let Some(Value::Sequence(options)) = node.get("options").map(|options| &options.value)
+            else {
+                continue;
+            };
+            for option in options {
+                let Value::Mapping(pairs) = &option.value else {
+                    continue;
+                };
+                let Some(next) = pairs.first().and_then(|(_, details)| details.get("goto")) else {
+                    continue;
+                };
+                if let Some(next_id) = next.as_str() {
+                    if !ids.contains(&next_id) {
+                        self.error(
+                            next,
+                            format!("there is no dialogue node called {}", next_id),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    // an event is triggered by a condition, by entering somewhere, or else by time alone
+    fn event_triggers(&mut self, root: &Node) {
+        let Value::Sequence(events) = &root.value else {
+            return;
+        };
+        for event in events {
+            let Value::Mapping(pairs) = &event.value else {
+                continue;
+            };
+            let Some((id, details)) = pairs.first() else {
+                continue;
+            };
+            if !matches!(details.value, Value::Mapping(_)) {
+                continue;
+            }
+            let id = id.as_str().unwrap_or("?");
+            let has = |key: &str| details.get(key).is_some();
+            if has("when") && has("enter") {
+                self.error(
+                    details,
+                    format!("event {} should have at most one of when and enter", id),
+                );
+            }
+            if !has("when") && !has("enter") && !has("after") {
+                self.error(
+                    details,
+                    format!("event {} should have a when, an enter or an after", id),
+                );
+            }
+        }
+    }
+}
+
+// ===================================================================================
+// SCHEMA
+//
+// json schemas for world files, for editors with a yaml language server. save one with
+// `wwizard schema location > location.schema.json` and point a file at it with a
+// `# yaml-language-server: $schema=<path>` comment at the top
+
+pub fn schema(file: WorldFile) -> String {
+    let mut root = object(vec![
+        ("$schema", text("http://json-schema.org/draft-07/schema#")),
+        ("title", Yaml::String(format!("wwizard {}", file.name()))),
+        ("description", text(file.about())),
+    ]);
+    if let Yaml::Hash(hash) = rule_schema(file.rule()) {
+        root.extend(hash);
+    }
+    root.insert(text("definitions"), Yaml::Hash(definitions()));
+
+    let mut out = String::new();
+    json(&Yaml::Hash(root), 0, &mut out);
+    out.push('\n');
+    out
+}
+
+fn object(pairs: Vec<(&str, Yaml)>) -> Hash {
+    pairs
+        .into_iter()
+        .map(|(key, value)| (text(key), value))
+        .collect()
+}
+
+fn text(s: &str) -> Yaml {
+    Yaml::String(s.into())
+}
+
+fn reference(definition: &str) -> Yaml {
+    Yaml::Hash(object(vec![(
+        "$ref",
+        Yaml::String(format!("#/definitions/{}", definition)),
+    )]))
+}
+
+fn strings(items: &[&str]) -> Yaml {
+    Yaml::Array(items.iter().map(|item| text(item)).collect())
+}
+
+fn rule_schema(rule: &Rule) -> Yaml {
+    let typed = |kind: &str, mut rest: Vec<(&str, Yaml)>| {
+        rest.insert(0, ("type", text(kind)));
+        Yaml::Hash(object(rest))
+    };
+    let whole_number = |minimum: i64| {
+        typed(
+            "integer",
+            vec![
+                ("minimum", Yaml::Integer(minimum)),
+                ("maximum", Yaml::Integer(u32::MAX.into())),
+            ],
+        )
+    };
+
+    match rule {
+        Rule::Text => typed("string", vec![]),
+        Rule::Flag => typed("boolean", vec![]),
+        Rule::Turns => whole_number(0),
+        Rule::Weight => whole_number(1),
+        Rule::Direction => typed(
+            "string",
+            vec![("pattern", Yaml::String(direction_pattern()))],
+        ),
+        Rule::Condition => reference("condition"),
+        Rule::Directives => reference("directives"),
+        Rule::Location => typed(
+            "string",
+            vec![(
+                "description",
+                text("A location id: the name of a location file, without its extension."),
+            )],
+        ),
+        Rule::Action => Yaml::Hash(object(vec![(
+            "anyOf",
+            Yaml::Array(vec![compact_action_schema(), rule_schema(&EXPLICIT_ACTION)]),
+        )])),
+        Rule::List { rule, .. } => typed("array", vec![("items", rule_schema(rule))]),
+        Rule::Map { rule, .. } => {
+            typed("object", vec![("additionalProperties", rule_schema(rule))])
+        }
+        Rule::Pair {
+            key_rule,
+            value_rule,
+            ..
+        } => {
+            let mut pair = vec![
+                ("minProperties", Yaml::Integer(1)),
+                ("maxProperties", Yaml::Integer(1)),
+            ];
+            // mapping keys are always strings to a json schema
+            match key_rule {
+                Rule::Direction => pair.push((
+                    "propertyNames",
+                    Yaml::Hash(object(vec![("pattern", Yaml::String(direction_pattern()))])),
+                )),
+                Rule::Weight => pair.push((
+                    "propertyNames",
+                    Yaml::Hash(object(vec![("pattern", text("^[1-9][0-9]*$"))])),
+                )),
+                _ => (),
+            }
+            pair.push(("additionalProperties", rule_schema(value_rule)));
+            typed("object", pair)
+        }
+        Rule::Fields(_, fields) => {
+            let properties = fields
+                .iter()
+                .map(|field| {
+                    let mut property = rule_schema(&field.rule);
+                    if let (Yaml::Hash(hash), false) = (&mut property, field.about.is_empty()) {
+                        hash.insert(text("description"), text(field.about));
+                    }
+                    (text(field.key), property)
+                })
+                .collect();
+            let required: Vec<&str> = fields
+                .iter()
+                .filter(|field| field.required)
+                .map(|field| field.key)
+                .collect();
+            let mut schema = vec![("additionalProperties", Yaml::Boolean(false))];
+            if !required.is_empty() {
+                schema.push(("required", strings(&required)));
+            }
+            schema.push(("properties", Yaml::Hash(properties)));
+            typed("object", schema)
+        }
+        Rule::Either { short, long } => Yaml::Hash(object(vec![(
+            "anyOf",
+            Yaml::Array(vec![rule_schema(short), rule_schema(long)]),
+        )])),
+    }
+}
+
+// title -> condition -> directives, with an optional else and fail beside the condition
+fn compact_action_schema() -> Yaml {
+    let details = object(vec![
+        ("type", text("object")),
+        ("minProperties", Yaml::Integer(1)),
+        ("maxProperties", Yaml::Integer(3)),
+        (
+            "propertyNames",
+            Yaml::Hash(object(vec![(
+                "anyOf",
+                Yaml::Array(vec![
+                    Yaml::Hash(object(vec![("enum", strings(&["else", "fail"]))])),
+                    reference("condition"),
+                ]),
+            )])),
+        ),
+        (
+            "properties",
+            Yaml::Hash(object(vec![
+                ("else", reference("directives")),
+                ("fail", rule_schema(&Rule::Text)),
+            ])),
+        ),
+        ("additionalProperties", reference("directives")),
+    ]);
+    Yaml::Hash(object(vec![
+        ("type", text("object")),
+        (
+            "description",
+            text("Compact form: title, then condition, then directives."),
+        ),
+        ("minProperties", Yaml::Integer(1)),
+        ("maxProperties", Yaml::Integer(1)),
+        ("additionalProperties", Yaml::Hash(details)),
+    ]))
+}
+
+fn definitions() -> Hash {
+    let mut random = rule_schema(&RANDOM);
+    if let Yaml::Hash(hash) = &mut random {
+        hash.insert(text("minItems"), Yaml::Integer(1));
+    }
+    object(vec![
+        (
+            "condition",
+            Yaml::Hash(object(vec![
+                ("type", text("string")),
+                (
+                    "description",
+                    text("always, a property id, or ! and a condition."),
+                ),
+                ("pattern", Yaml::String(condition_pattern())),
+            ])),
+        ),
+        (
+            "directive",
+            Yaml::Hash(object(vec![(
+                "anyOf",
+                Yaml::Array(vec![
+                    Yaml::Hash(object(vec![
+                        ("type", text("string")),
+                        (
+                            "description",
+                            text("set <property> true|false, toggle <property>, goto <location>, say \"...\", win [\"...\"], lose [\"...\"] or describe."),
+                        ),
+                        ("pattern", Yaml::String(directive_pattern())),
+                    ])),
+                    Yaml::Hash(object(vec![
+                        ("type", text("object")),
+                        ("description", text("Pick one branch at random, weighted.")),
+                        ("required", strings(&["random"])),
+                        ("additionalProperties", Yaml::Boolean(false)),
+                        ("properties", Yaml::Hash(object(vec![("random", random)]))),
+                    ])),
+                ]),
+            )])),
+        ),
+        (
+            "directives",
+            Yaml::Hash(object(vec![
+                ("type", text("array")),
+                ("items", reference("directive")),
+            ])),
+        ),
+    ])
+}
+
+// the patterns are built from what the parser uses, so they accept the same things
+
+fn property_pattern() -> String {
+    let scope: String = PLAYER_SCOPE
+        .chars()
+        .map(|c| match c {
+            '$' | '.' => format!("\\{}", c),
+            c => c.to_string(),
+        })
+        .collect();
+    format!("({})?[{}]+", scope, PROPERTY_CHARS)
+}
+
+fn condition_pattern() -> String {
+    format!("^!*{}$", property_pattern())
+}
+
+fn directive_pattern() -> String {
+    let property = property_pattern();
+    format!(
+        "^\\s*(set\\s+{p}\\s+(true|false)|toggle\\s+{p}|goto\\s+\\S+|say\\s+\".*\"|(win|lose)(\\s+\".*\")?|describe)\\s*$",
+        p = property
+    )
+}
+
+// from_word ignores case, which a json schema regex can't be told to, so each letter
+// matches either way
+fn direction_pattern() -> String {
+    let words: Vec<String> = DIRECTION_WORDS
+        .iter()
+        .map(|(word, _)| {
+            word.chars()
+                .map(|c| format!("[{}{}]", c, c.to_ascii_uppercase()))
+                .collect()
+        })
+        .collect();
+    format!("^({})$", words.join("|"))
+}
+
+// yaml_rust2 only emits yaml, and a schema only needs mappings, lists, strings,
+// numbers and booleans
+fn json(value: &Yaml, indent: usize, out: &mut String) {
+    let pad = "  ".repeat(indent);
+    match value {
+        Yaml::Hash(hash) if hash.is_empty() => out.push_str("{}"),
+        Yaml::Hash(hash) => {
+            out.push_str("{\n");
+            for (i, (key, value)) in hash.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&pad);
+                out.push_str("  ");
+                json(key, indent + 1, out);
+                out.push_str(": ");
+                json(value, indent + 1, out);
+            }
+            out.push('\n');
+            out.push_str(&pad);
+            out.push('}');
+        }
+        // short lists of strings, like required, stay on one line
+        Yaml::Array(items) if items.iter().all(|item| matches!(item, Yaml::String(_))) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                json(item, indent, out);
+            }
+            out.push(']');
+        }
+        Yaml::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(",\n");
+                }
+                out.push_str(&pad);
+                out.push_str("  ");
+                json(item, indent + 1, out);
+            }
+            out.push('\n');
+            out.push_str(&pad);
+            out.push(']');
+        }
+        Yaml::String(s) => {
+            out.push('"');
+            for c in s.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+        }
+        Yaml::Integer(n) => out.push_str(&n.to_string()),
+        Yaml::Boolean(b) => out.push_str(&b.to_string()),
+        _ => out.push_str("null"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_location(source: &str) -> Vec<String> {
+        let mut checker = Checker {
+            file: "test.yaml".into(),
+            ..Checker::default()
+        };
+        checker.check_source(source, WorldFile::Location);
+        checker.check_references();
+        checker
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_check_location() {
+        let diagnostics = check_location(
+            "description: A test room.
+actions:
+- unlock door:
+    hasKey:
+    - set isDoorOpen maybe
+    hasCrowbar:
+    - goto shed
+exits:
+- sideways: shed
+",
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                "test.yaml:5:7: error: Set directive property value should either be true or false",
+                "test.yaml:6:5: error: action should have a single condition; split it into separate actions or use the explicit form",
+                "test.yaml:9:3: error: exit direction sideways should be a compass direction, up, down, in or out",
+                "test.yaml:9:13: warning: there is no location called shed",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_shared_properties() {
+        let diagnostics = check_location(
+            "description: A test room.
+actions:
+- title: take key
+  do:
+  - set hasKey true
+  - set $me.hasLamp true
+  - toggle isDoorOpen
+",
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                "test.yaml:5:5: warning: hasKey is shared by every player in serve --shared; write $me.hasKey to give each player their own",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_yaml_syntax() {
+        let diagnostics = check_location("description: [unclosed\n");
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("test.yaml:2:"));
+    }
+
+    #[test]
+    fn test_check_assets() {
+        // the hallway's action leads to a location nobody has written yet
+        let diagnostics = check_world("assets");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].message, "there is no location called hell");
+    }
+
+    #[test]
+    fn test_check_agrees_with_loader() {
+        // the loaders reject the first three and accept the last three
+        let diagnostics = check_location(
+            "description: A test room.
+actions:
+- title: 5
+  hidden: 'true'
+  do:
+  - random:
+    - -1:
+      - describe
+- title: open door
+  do:
+  - goto back-door
+  - set North_1 true
+- title:
+    always:
+    - describe
+",
+        );
+        assert_eq!(
+            diagnostics,
+            vec![
+                "test.yaml:3:10: error: action title should be a string",
+                "test.yaml:4:11: error: action hidden should be true or false",
+                "test.yaml:7:7: error: random branch weight should be a whole number from 1 to 4294967295",
+                "test.yaml:11:5: warning: there is no location called back-door",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schema_is_json() {
+        for file in WorldFile::ALL {
+            let docs = yaml_rust2::YamlLoader::load_from_str(&schema(file)).unwrap();
+            assert_eq!(
+                docs[0]["title"].as_str(),
+                Some(format!("wwizard {}", file.name()).as_str())
+            );
+            assert!(!docs[0]["definitions"]["directive"].is_badvalue());
+            assert!(!docs[0]["definitions"]["condition"].is_badvalue());
+        }
+        assert_eq!(condition_pattern(), "^!*(\\$me\\.)?[A-Za-z0-9_]+$");
+    }
+}
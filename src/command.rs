@@ -0,0 +1,328 @@
+use std::fmt;
+
+use crate::parser::{Direction, Object};
+
+// ===================================================================================
+// VOCABULARY
+
+// verbs with more than one way of saying them. the first entry is the canonical form;
+// any verb that isn't listed here is taken as-is
+const VERBS: &[&[&str]] = &[
+    &["look", "l"],
+    &["examine", "x", "inspect", "look at", "check"],
+    &["inventory", "inv", "i"],
+    &["help", "?"],
+    &["wait", "z"],
+    &["again", "g"],
+    &["go", "walk", "move", "head", "enter"],
+    &["take", "get", "grab", "pick up"],
+    &["drop", "put down"],
+    &["talk", "talk to", "talk with", "speak to", "speak with"],
+    &["read"],
+    &["open"],
+    &["close", "shut"],
+    &["use"],
+];
+
+// words that come before a noun but don't say anything about which thing is meant
+const DETERMINERS: &[&str] = &[
+    "the", "a", "an", "some", "my", "your", "that", "this", "these", "those",
+];
+
+const PREPOSITIONS: &[&str] = &[
+    "in", "into", "inside", "on", "onto", "off", "with", "to", "at", "under", "behind", "from",
+    "about", "through",
+];
+
+// stand-ins for whatever the player last mentioned
+const PRONOUNS: &[&str] = &["it", "them", "him", "her"];
+
+// ===================================================================================
+// COMMANDS
+
+// one thing the player asked to do, e.g. "unlock the oak door with the brass key"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    // canonical verb, e.g. "take" for "pick up"
+    pub verb: String,
+    pub object: Option<NounPhrase>,
+    pub preposition: Option<String>,
+    pub indirect: Option<NounPhrase>,
+}
+
+// a noun and whatever adjectives narrow it down, e.g. "oak door"
+#[derive(Debug, Clone, PartialEq)]
+pub struct NounPhrase {
+    pub adjectives: Vec<String>,
+    pub noun: String,
+}
+
+// what a noun phrase refers to in the current location
+pub enum Resolution<'a> {
+    Found(&'a Object),
+    Ambiguous(Vec<&'a Object>),
+    Unknown,
+}
+
+impl Command {
+    // None if there's nothing to parse
+    pub fn parse(input: &str) -> Option<Command> {
+        let words: Vec<String> = input
+            .split_whitespace()
+            .map(|word| {
+                let word = word.to_ascii_lowercase();
+                if word == "?" {
+                    word
+                } else {
+                    word.trim_matches(|c: char| ",.!?;:\"".contains(c)).to_string()
+                }
+            })
+            .filter(|word| !word.is_empty())
+            .collect();
+        if words.is_empty() {
+            return None;
+        }
+        let words: Vec<&str> = words.iter().map(String::as_str).collect();
+
+        // a bare direction is shorthand for going that way
+        if let [word] = words.as_slice() {
+            if let Some(direction) = Direction::from_word(word) {
+                return Some(Command::go(direction));
+            }
+        }
+
+        let (verb, rest) = split_verb(&words);
+        if verb == "go" {
+            // before looking for prepositions, since "in" is one too: "go in", "go n"
+            // (the same as "go north"), and "enter" by itself
+            let direction = match NounPhrase::from_words(rest) {
+                Some(phrase) if phrase.adjectives.is_empty() => Direction::from_word(&phrase.noun),
+                None if words == ["enter"] => Some(Direction::In),
+                _ => None,
+            };
+            if let Some(direction) = direction {
+                return Some(Command::go(direction));
+            }
+        }
+
+        // everything up to the first preposition is the direct object
+        let (object_words, preposition, indirect_words) =
+            match rest.iter().position(|word| PREPOSITIONS.contains(word)) {
+                Some(i) => (&rest[..i], Some(rest[i].to_string()), &rest[i + 1..]),
+                None => (rest, None, &rest[rest.len()..]),
+            };
+
+        Some(Command {
+            verb,
+            object: NounPhrase::from_words(object_words),
+            preposition,
+            indirect: NounPhrase::from_words(indirect_words),
+        })
+    }
+
+    fn go(direction: Direction) -> Command {
+        Command {
+            verb: "go".into(),
+            object: Some(NounPhrase::new(&direction.to_string())),
+            preposition: None,
+            indirect: None,
+        }
+    }
+
+    // does what the player typed fit the pattern of an action's title? the player
+    // can leave out adjectives, and can add a prepositional phrase the action doesn't care about
+    pub fn matches(&self, pattern: &Command) -> bool {
+        let phrases_match = |ours: &Option<NounPhrase>, theirs: &Option<NounPhrase>| match (
+            ours, theirs,
+        ) {
+            (Some(ours), Some(theirs)) => ours.matches(theirs),
+            (None, None) => true,
+            _ => false,
+        };
+
+        self.verb == pattern.verb
+            && phrases_match(&self.object, &pattern.object)
+            && (pattern.indirect.is_none()
+                || (self.preposition == pattern.preposition
+                    && phrases_match(&self.indirect, &pattern.indirect)))
+    }
+}
+
+impl NounPhrase {
+    // the phrase naming an object, e.g. "glass baubles"
+    pub fn new(name: &str) -> NounPhrase {
+        let words: Vec<&str> = name.split_whitespace().collect();
+        NounPhrase::from_words(&words).unwrap_or(NounPhrase {
+            adjectives: vec![],
+            noun: name.to_ascii_lowercase(),
+        })
+    }
+
+    fn from_words(words: &[&str]) -> Option<NounPhrase> {
+        let words: Vec<String> = words
+            .iter()
+            .filter(|word| !DETERMINERS.contains(word))
+            .map(|word| word.to_ascii_lowercase())
+            .collect();
+        let (noun, adjectives) = words.split_last()?;
+        Some(NounPhrase {
+            adjectives: adjectives.to_vec(),
+            noun: noun.clone(),
+        })
+    }
+
+    pub fn is_pronoun(&self) -> bool {
+        self.adjectives.is_empty() && PRONOUNS.contains(&self.noun.as_str())
+    }
+
+    // same noun, and one phrase's adjectives are a subset of the other's
+    pub fn matches(&self, other: &NounPhrase) -> bool {
+        let subset = |a: &[String], b: &[String]| a.iter().all(|adjective| b.contains(adjective));
+        self.noun == other.noun
+            && (subset(&self.adjectives, &other.adjectives)
+                || subset(&other.adjectives, &self.adjectives))
+    }
+
+    // find which of the objects here the phrase refers to
+    pub fn resolve<'a>(&self, objects: &'a [Object]) -> Resolution<'a> {
+        let candidates: Vec<&Object> = objects
+            .iter()
+            .filter(|object| self.matches(&NounPhrase::new(&object.name)))
+            .collect();
+
+        // an exact name beats a partial one ("lamp" over "lamp shade" if both exist)
+        if let Some(exact) = candidates
+            .iter()
+            .find(|object| NounPhrase::new(&object.name) == *self)
+        {
+            return Resolution::Found(exact);
+        }
+        match candidates.as_slice() {
+            [] => Resolution::Unknown,
+            [object] => Resolution::Found(object),
+            _ => Resolution::Ambiguous(candidates),
+        }
+    }
+}
+
+impl fmt::Display for NounPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for adjective in &self.adjectives {
+            write!(f, "{} ", adjective)?;
+        }
+        write!(f, "{}", self.noun)
+    }
+}
+
+// "take key then go north. look" -> ["take key", "go north", "look"]
+pub fn split_commands(input: &str) -> Vec<String> {
+    input
+        .split(['.', ';'])
+        .flat_map(|sentence| {
+            let mut commands = vec![];
+            let mut current: Vec<&str> = vec![];
+            for word in sentence.split_whitespace() {
+                if word.eq_ignore_ascii_case("then") {
+                    commands.push(current.join(" "));
+                    current.clear();
+                } else {
+                    current.push(word);
+                }
+            }
+            commands.push(current.join(" "));
+            commands
+        })
+        .map(|command| command.trim_matches([' ', ',']).to_string())
+        .filter(|command| !command.is_empty())
+        .collect()
+}
+
+// the longest known verb phrase at the start of the input, or just the first word
+fn split_verb<'a, 'b>(words: &'a [&'b str]) -> (String, &'a [&'b str]) {
+    let mut best: Option<(&str, usize)> = None;
+    for synonyms in VERBS {
+        for synonym in synonyms.iter() {
+            let synonym_words: Vec<&str> = synonym.split(' ').collect();
+            let length = synonym_words.len();
+            if words.starts_with(&synonym_words) && best.is_none_or(|(_, best)| length > best) {
+                best = Some((synonyms[0], length));
+            }
+        }
+    }
+    match best {
+        Some((verb, length)) => (verb.to_string(), &words[length..]),
+        None => (words[0].to_string(), &words[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn object(name: &str) -> Object {
+        Object {
+            name: name.into(),
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_command() {
+        let command = Command::parse("Unlock the oak door with the brass key!").unwrap();
+        assert_eq!(command.verb, "unlock");
+        assert_eq!(command.object, Some(NounPhrase::new("oak door")));
+        assert_eq!(command.preposition.as_deref(), Some("with"));
+        assert_eq!(command.indirect, Some(NounPhrase::new("brass key")));
+
+        let command = Command::parse("pick up the lamp").unwrap();
+        assert_eq!(command.verb, "take");
+        assert_eq!(command.object, Some(NounPhrase::new("lamp")));
+
+        assert_eq!(Command::parse("look at it").unwrap().verb, "examine");
+        assert!(Command::parse("look at it").unwrap().object.unwrap().is_pronoun());
+
+        assert_eq!(Command::parse("n"), Command::parse("go north"));
+        assert_eq!(Command::parse("walk n"), Command::parse("go north"));
+        assert_eq!(Command::parse("   "), None);
+    }
+
+    #[test]
+    fn test_command_matches_action_title() {
+        let action = Command::parse("read letter").unwrap();
+        assert!(Command::parse("read the letter").unwrap().matches(&action));
+        assert!(Command::parse("read the letter on my desk")
+            .unwrap()
+            .matches(&action));
+        assert!(!Command::parse("read the book").unwrap().matches(&action));
+
+        let action = Command::parse("unlock door with key").unwrap();
+        assert!(Command::parse("unlock the oak door with the brass key")
+            .unwrap()
+            .matches(&action));
+        assert!(!Command::parse("unlock door").unwrap().matches(&action));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let objects = vec![object("glass baubles"), object("red baubles"), object("lamp")];
+        let resolve = |input: &str| NounPhrase::new(input).resolve(&objects);
+
+        assert!(matches!(resolve("lamp"), Resolution::Found(o) if o.name == "lamp"));
+        assert!(matches!(resolve("glass baubles"), Resolution::Found(o) if o.name == "glass baubles"));
+        assert!(matches!(resolve("baubles"), Resolution::Ambiguous(os) if os.len() == 2));
+        assert!(matches!(resolve("pen"), Resolution::Unknown));
+    }
+
+    #[test]
+    fn test_split_commands() {
+        assert_eq!(
+            split_commands("take key then go north"),
+            vec!["take key", "go north"]
+        );
+        assert_eq!(
+            split_commands("read letter, then east. look"),
+            vec!["read letter", "east", "look"]
+        );
+        assert_eq!(split_commands(""), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,655 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::{Color, Modifier, Style};
+use wwizard::engine::{ERROR, HINT, NARRATOR, SYSTEM};
+use wwizard::World;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::app::MessageType;
+
+// the user's settings for the terminal app, e.g.
+//
+//      preset: high-contrast       # or monochrome; default otherwise
+//      colours:
+//        border: lightblue
+//        user: bold white
+//        narrator: "#7fffff"     # or hint, system, error, speech, or anyone who speaks
+//        match: black on yellow    # search matches; selection is the picked message
+//        hover: reversed           # the action or exit under the mouse; button for the rest
+//      border: "#"
+//      padding:
+//        horizontal: 0.05          # of the screen's width, on each side
+//        vertical: 0.0667
+//      keys:
+//        scroll_up: [up, ctrl+k]
+//
+// anything left out keeps its preset's value
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    pub theme: Theme,
+    pub keys: KeyBindings,
+}
+
+// the category everyone who speaks is drawn in, unless they have their own style
+const SPEECH: &str = "speech";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub border: Style,
+    // by message category: user, narrator, speech, hint, system, error, or a speaker's name
+    pub messages: HashMap<String, Style>,
+    // the world's own styles for its voices, used when messages has nothing for them
+    pub voices: HashMap<String, Style>,
+    // whether the world's voices keep their colours, or only their weight and slant
+    pub voice_colours: bool,
+    pub won: Style,
+    pub lost: Style,
+    // the message picked out while selecting, or the current search match
+    pub selection: Style,
+    // every piece of text a search matches
+    pub search_match: Style,
+    // the actions and exits that can be clicked, and the one under the mouse
+    pub button: Style,
+    pub hover: Style,
+    // drawn along the top of the message area
+    pub border_glyph: String,
+    // fractions of the screen left empty on each side
+    pub horizontal_padding: f32,
+    pub vertical_padding: f32,
+}
+
+// things a key can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAction {
+    // by a line, a page, or all the way
+    ScrollUp,
+    ScrollDown,
+    PageUp,
+    PageDown,
+    ScrollTop,
+    ScrollBottom,
+    CursorLeft,
+    CursorRight,
+    DeleteChar,
+    Submit,
+    // any of these leave the ending screen
+    LeaveEnding,
+    // search the messages, and go to the next (older) or previous match
+    Search,
+    NextMatch,
+    PrevMatch,
+    // pick a message with the scroll keys, to copy it
+    Select,
+    Copy,
+    // stop searching or selecting
+    Cancel,
+}
+
+const KEY_ACTIONS: [(&str, KeyAction); 17] = [
+    ("scroll_up", KeyAction::ScrollUp),
+    ("scroll_down", KeyAction::ScrollDown),
+    ("page_up", KeyAction::PageUp),
+    ("page_down", KeyAction::PageDown),
+    ("scroll_top", KeyAction::ScrollTop),
+    ("scroll_bottom", KeyAction::ScrollBottom),
+    ("cursor_left", KeyAction::CursorLeft),
+    ("cursor_right", KeyAction::CursorRight),
+    ("delete_char", KeyAction::DeleteChar),
+    ("submit", KeyAction::Submit),
+    ("leave_ending", KeyAction::LeaveEnding),
+    ("search", KeyAction::Search),
+    ("next_match", KeyAction::NextMatch),
+    ("prev_match", KeyAction::PrevMatch),
+    ("select", KeyAction::Select),
+    ("copy", KeyAction::Copy),
+    ("cancel", KeyAction::Cancel),
+];
+
+// a key, with whatever modifiers have to be held with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings(HashMap<KeyAction, Vec<KeyBinding>>);
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            border: Style::new().fg(Color::LightBlue),
+            messages: messages([
+                ("user", Style::new().fg(Color::White)),
+                (NARRATOR, Style::new().fg(Color::LightCyan)),
+                (SPEECH, Style::new().fg(Color::Yellow)),
+                (HINT, Style::new().fg(Color::Cyan)),
+                (SYSTEM, Style::new().fg(Color::Gray)),
+                (ERROR, Style::new().fg(Color::LightRed)),
+            ]),
+            voices: HashMap::new(),
+            voice_colours: true,
+            won: Style::new().fg(Color::LightGreen),
+            lost: Style::new().fg(Color::Red),
+            selection: Style::new().add_modifier(Modifier::REVERSED),
+            search_match: Style::new().fg(Color::Black).bg(Color::Yellow),
+            button: Style::new().fg(Color::LightBlue),
+            hover: Style::new().fg(Color::Black).bg(Color::LightBlue),
+            border_glyph: "#".into(),
+            horizontal_padding: 1.0 / 20.0,
+            vertical_padding: 1.0 / 15.0,
+        }
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        let key = |code| KeyBinding {
+            code,
+            modifiers: KeyModifiers::NONE,
+        };
+        let ctrl = |c| KeyBinding {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        };
+        KeyBindings(HashMap::from([
+            (KeyAction::ScrollUp, vec![key(KeyCode::Up)]),
+            (KeyAction::ScrollDown, vec![key(KeyCode::Down)]),
+            (KeyAction::PageUp, vec![key(KeyCode::PageUp)]),
+            (KeyAction::PageDown, vec![key(KeyCode::PageDown)]),
+            (KeyAction::ScrollTop, vec![key(KeyCode::Home)]),
+            (KeyAction::ScrollBottom, vec![key(KeyCode::End)]),
+            (KeyAction::CursorLeft, vec![key(KeyCode::Left)]),
+            (KeyAction::CursorRight, vec![key(KeyCode::Right)]),
+            (KeyAction::DeleteChar, vec![key(KeyCode::Backspace)]),
+            (KeyAction::Submit, vec![key(KeyCode::Enter)]),
+            (
+                KeyAction::LeaveEnding,
+                vec![
+                    key(KeyCode::Enter),
+                    key(KeyCode::Esc),
+                    key(KeyCode::Char('q')),
+                ],
+            ),
+            // only when nothing has been typed, so / can still be typed
+            (KeyAction::Search, vec![key(KeyCode::Char('/'))]),
+            (KeyAction::NextMatch, vec![ctrl('n')]),
+            (KeyAction::PrevMatch, vec![ctrl('p')]),
+            (KeyAction::Select, vec![key(KeyCode::Tab)]),
+            (KeyAction::Copy, vec![ctrl('y')]),
+            (KeyAction::Cancel, vec![key(KeyCode::Esc)]),
+        ]))
+    }
+}
+
+impl Theme {
+    pub fn preset(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::default()),
+            // bright, bold, and no blue on black
+            "high-contrast" => Some(Theme {
+                border: Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+                messages: messages([
+                    (
+                        "user",
+                        Style::new().fg(Color::White).add_modifier(Modifier::BOLD),
+                    ),
+                    (NARRATOR, Style::new().fg(Color::LightYellow)),
+                    (SPEECH, Style::new().fg(Color::LightMagenta)),
+                    (HINT, Style::new().fg(Color::LightCyan)),
+                    (SYSTEM, Style::new().fg(Color::White)),
+                    (
+                        ERROR,
+                        Style::new()
+                            .fg(Color::LightRed)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                ]),
+                won: Style::new()
+                    .fg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+                lost: Style::new()
+                    .fg(Color::LightRed)
+                    .add_modifier(Modifier::BOLD),
+                ..Theme::default()
+            }),
+            // the terminal's own colours; message types are told apart by weight instead
+            "monochrome" => Some(Theme {
+                border: Style::new(),
+                messages: messages([
+                    ("user", Style::new().add_modifier(Modifier::BOLD)),
+                    (NARRATOR, Style::new()),
+                    (SPEECH, Style::new().add_modifier(Modifier::ITALIC)),
+                    (HINT, Style::new().add_modifier(Modifier::DIM)),
+                    (SYSTEM, Style::new().add_modifier(Modifier::DIM)),
+                    (ERROR, Style::new().add_modifier(Modifier::BOLD)),
+                ]),
+                voice_colours: false,
+                won: Style::new().add_modifier(Modifier::BOLD),
+                lost: Style::new().add_modifier(Modifier::BOLD),
+                search_match: Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                button: Style::new(),
+                hover: Style::new().add_modifier(Modifier::REVERSED),
+                ..Theme::default()
+            }),
+            _ => None,
+        }
+    }
+
+    // the theme's own style for a category, then the world's, then the one every
+    // speaker shares. the engine's categories always have one
+    pub fn message(&self, msg_type: &MessageType) -> Style {
+        let category = msg_type.category();
+        self.messages
+            .get(category)
+            .or_else(|| self.voices.get(category))
+            .or_else(|| self.messages.get(SPEECH))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // take the styles the world gives its voices, skipping any that don't parse
+    pub fn add_voices(&mut self, world: &World) {
+        for voice in &world.voices {
+            let Some(Ok(mut style)) = voice.style.as_deref().map(parse_style) else {
+                continue;
+            };
+            if !self.voice_colours {
+                style.fg = None;
+                style.bg = None;
+            }
+            self.voices.insert(voice.name.clone(), style);
+        }
+    }
+}
+
+fn messages<const N: usize>(styles: [(&str, Style); N]) -> HashMap<String, Style> {
+    styles
+        .into_iter()
+        .map(|(category, style)| (category.to_string(), style))
+        .collect()
+}
+
+impl KeyBindings {
+    // a key bound to two actions only ever does one of them. leave_ending is the
+    // exception: it only applies once the game is over, when nothing else does
+    fn check(&self) -> Result<(), String> {
+        let bindings = |action| self.0.get(action).into_iter().flatten();
+        for (i, (name, action)) in KEY_ACTIONS.iter().enumerate() {
+            for (other_name, other) in &KEY_ACTIONS[i + 1..] {
+                if [action, other].contains(&&KeyAction::LeaveEnding) {
+                    continue;
+                }
+                if bindings(action).any(|binding| bindings(other).any(|b| b == binding)) {
+                    return Err(format!(
+                        "{} and {} are bound to the same key; give each its own",
+                        name, other_name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // the keys that do an action, for a hint: "enter, esc or q"
+    pub fn describe(&self, action: KeyAction) -> Option<String> {
+        let names: Vec<String> = self.0.get(&action)?.iter().map(|b| b.to_string()).collect();
+        match names.split_last() {
+            Some((last, [])) => Some(last.clone()),
+            Some((last, rest)) => Some(format!("{} or {}", rest.join(", "), last)),
+            None => None,
+        }
+    }
+
+    pub fn matches(&self, action: KeyAction, event: &KeyEvent) -> bool {
+        self.0.get(&action).is_some_and(|bindings| {
+            bindings.iter().any(|binding| {
+                binding.code == event.code
+                    // shift is part of the character for letters and symbols
+                    && binding.modifiers == event.modifiers.difference(KeyModifiers::SHIFT)
+            })
+        })
+    }
+}
+
+// the way the config names it, so hints name the keys actually bound
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (modifier, name) in [(KeyModifiers::CONTROL, "ctrl"), (KeyModifiers::ALT, "alt")] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.code {
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            // up, down, pageup and the rest
+            code => write!(f, "{}", format!("{:?}", code).to_lowercase()),
+        }
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    // "enter", "pageup", "f2", "x", "ctrl+p", "alt+shift+tab"
+    fn from_str(s: &str) -> Result<KeyBinding, String> {
+        let mut parts: Vec<&str> = s.split('+').collect();
+        // "+" on its own, or "ctrl++"
+        if s.ends_with("++") || s == "+" {
+            parts.truncate(parts.len() - 2);
+            parts.push("+");
+        }
+        let key = parts.pop().unwrap_or_default();
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in parts {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier {} in {}", modifier, s)),
+            };
+        }
+
+        let code = match key.to_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            lower => {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => match lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                        Some(n) => KeyCode::F(n),
+                        None => return Err(format!("unknown key {}", s)),
+                    },
+                }
+            }
+        };
+        Ok(KeyBinding {
+            code,
+            // shift is ignored when matching, since it's already in the character
+            modifiers: modifiers.difference(KeyModifiers::SHIFT),
+        })
+    }
+}
+
+impl Config {
+    // read the config file: WWIZARD_CONFIG if it's set, otherwise wwizard/config.yaml in
+    // the user's config directory. no file just means the defaults. NO_COLOR
+    // (https://no-color.org) starts from the monochrome preset
+    pub fn from_env() -> io::Result<Config> {
+        let mut config = Config::default();
+        if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            config.theme = Theme::preset("monochrome").expect("monochrome preset should exist");
+        }
+
+        let path = match env::var_os("WWIZARD_CONFIG") {
+            Some(path) => PathBuf::from(path),
+            None => match config_dir() {
+                Some(dir) => dir.join("wwizard").join("config.yaml"),
+                None => return Ok(config),
+            },
+        };
+        if !path.exists() {
+            return Ok(config);
+        }
+
+        let source = fs::read_to_string(&path)?;
+        config.apply(&source).map_err(|e| {
+            io::Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+        })?;
+        Ok(config)
+    }
+
+    // layer a config file's settings over these. errors are messages for the user
+    pub fn apply(&mut self, source: &str) -> Result<(), String> {
+        let docs = YamlLoader::load_from_str(source).map_err(|e| e.to_string())?;
+        let Some(doc) = docs.first() else {
+            return Ok(());
+        };
+        let Yaml::Hash(mapping) = doc else {
+            return Err("config should be a YAML mapping".into());
+        };
+        for key in mapping.keys() {
+            match key.as_str() {
+                Some("preset" | "colours" | "colors" | "border" | "padding" | "keys") => (),
+                _ => return Err(format!("unknown setting {:?}", key)),
+            }
+        }
+
+        if let Some(preset) = optional_str(doc, "preset")? {
+            self.theme =
+                Theme::preset(preset).ok_or_else(|| format!("unknown preset {}", preset))?;
+        }
+
+        let colours = match (&doc["colours"], &doc["colors"]) {
+            (Yaml::BadValue, colours) | (colours, _) => colours,
+        };
+        if let Yaml::Hash(colours) = colours {
+            for (name, style) in colours {
+                let style = parse_style(
+                    style
+                        .as_str()
+                        .ok_or_else(|| format!("colour {:?} should be a string", name))?,
+                )?;
+                match name.as_str() {
+                    Some("border") => self.theme.border = style,
+                    Some("won") => self.theme.won = style,
+                    Some("lost") => self.theme.lost = style,
+                    Some("selection") => self.theme.selection = style,
+                    Some("match") => self.theme.search_match = style,
+                    Some("button") => self.theme.button = style,
+                    Some("hover") => self.theme.hover = style,
+                    // what the narrator's messages were called before there were others
+                    Some("game") => {
+                        self.theme.messages.insert(NARRATOR.into(), style);
+                    }
+                    Some(category) => {
+                        self.theme.messages.insert(category.into(), style);
+                    }
+                    None => return Err(format!("colour names should be strings, not {:?}", name)),
+                }
+            }
+        }
+
+        if let Some(glyph) = optional_str(doc, "border")? {
+            self.theme.border_glyph = glyph.into();
+        }
+
+        for (name, padding) in [
+            ("horizontal", &mut self.theme.horizontal_padding),
+            ("vertical", &mut self.theme.vertical_padding),
+        ] {
+            let value = match &doc["padding"][name] {
+                Yaml::BadValue => continue,
+                Yaml::Integer(n) => *n as f64,
+                yaml => yaml
+                    .as_f64()
+                    .ok_or_else(|| format!("padding.{} should be a number", name))?,
+            };
+            if !(0.0..0.5).contains(&value) {
+                return Err(format!("padding.{} should be between 0 and 0.5", name));
+            }
+            *padding = value as f32;
+        }
+
+        if let Yaml::Hash(keys) = &doc["keys"] {
+            for (name, bindings) in keys {
+                let action = KEY_ACTIONS
+                    .iter()
+                    .find(|(action_name, _)| name.as_str() == Some(action_name))
+                    .map(|(_, action)| *action)
+                    .ok_or_else(|| format!("unknown key action {:?}", name))?;
+                let bindings = match bindings {
+                    Yaml::String(binding) => vec![binding.parse()?],
+                    Yaml::Array(bindings) => bindings
+                        .iter()
+                        .map(|binding| {
+                            binding
+                                .as_str()
+                                .ok_or_else(|| format!("keys for {:?} should be strings", name))?
+                                .parse()
+                        })
+                        .collect::<Result<_, String>>()?,
+                    _ => return Err(format!("keys for {:?} should be a list", name)),
+                };
+                self.keys.0.insert(action, bindings);
+            }
+        }
+        self.keys.check()
+    }
+}
+
+fn optional_str<'a>(doc: &'a Yaml, key: &str) -> Result<Option<&'a str>, String> {
+    match &doc[key] {
+        Yaml::BadValue => Ok(None),
+        Yaml::String(s) => Ok(Some(s)),
+        _ => Err(format!("{} should be a string", key)),
+    }
+}
+
+// "lightcyan", "bold white", "#7fffff", "italic", "black on yellow"
+fn parse_style(s: &str) -> Result<Style, String> {
+    let mut style = Style::new();
+    let mut words = s.split_whitespace();
+    while let Some(word) = words.next() {
+        style = match word.to_lowercase().as_str() {
+            "on" => {
+                let background = words
+                    .next()
+                    .ok_or_else(|| format!("{} should name a colour after \"on\"", s))?;
+                style.bg(Color::from_str(background)
+                    .map_err(|_| format!("unknown colour {}", background))?)
+            }
+            "bold" => style.add_modifier(Modifier::BOLD),
+            "dim" => style.add_modifier(Modifier::DIM),
+            "italic" => style.add_modifier(Modifier::ITALIC),
+            "underlined" => style.add_modifier(Modifier::UNDERLINED),
+            "reversed" => style.add_modifier(Modifier::REVERSED),
+            _ => style.fg(Color::from_str(word).map_err(|_| format!("unknown colour {}", word))?),
+        };
+    }
+    Ok(style)
+}
+
+// $XDG_CONFIG_HOME, or ~/.config
+fn config_dir() -> Option<PathBuf> {
+    match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_config() {
+        let mut config = Config::default();
+        config
+            .apply(
+                "preset: monochrome
+colours:
+  narrator: 'bold #7fffff'
+  jesse: underlined
+  match: black on yellow
+border: '='
+padding:
+  horizontal: 0
+keys:
+  scroll_up: [up, ctrl+k]
+  submit: ctrl+s
+",
+            )
+            .unwrap();
+
+        let style =
+            |theme: &Theme, category: &str| theme.message(&MessageType::Game(category.into()));
+        assert_eq!(
+            config.theme.message(&MessageType::User),
+            Style::new().add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(
+            style(&config.theme, NARRATOR),
+            Style::new()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Rgb(0x7f, 0xff, 0xff))
+        );
+
+        // the world's voices are styled by it, minus their colours in monochrome, but
+        // the config has the last word
+        config.theme.add_voices(&World::load("assets"));
+        assert_eq!(style(&config.theme, "Marigold"), Style::new());
+        assert_eq!(
+            style(&config.theme, "jesse"),
+            Style::new().add_modifier(Modifier::UNDERLINED)
+        );
+        assert_eq!(
+            style(&config.theme, "Gregor"),
+            Style::new().add_modifier(Modifier::ITALIC)
+        );
+        assert_eq!(
+            config.theme.search_match,
+            Style::new().fg(Color::Black).bg(Color::Yellow)
+        );
+        assert_eq!(config.theme.border_glyph, "=");
+        assert_eq!(config.theme.horizontal_padding, 0.0);
+
+        let ctrl_k = KeyEvent::new(KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert!(config.keys.matches(KeyAction::ScrollUp, &ctrl_k));
+        assert!(!config.keys.matches(KeyAction::ScrollDown, &ctrl_k));
+        let enter = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        assert!(!config.keys.matches(KeyAction::Submit, &enter));
+        assert!(config.keys.matches(KeyAction::LeaveEnding, &enter));
+        assert_eq!(
+            KeyBindings::default()
+                .describe(KeyAction::LeaveEnding)
+                .as_deref(),
+            Some("enter, esc or q")
+        );
+        assert_eq!(
+            config.keys.describe(KeyAction::ScrollUp).as_deref(),
+            Some("up or ctrl+k")
+        );
+        let mut rebound = Config::default();
+        rebound.apply("keys: {leave_ending: alt+f2}").unwrap();
+        assert_eq!(
+            rebound.keys.describe(KeyAction::LeaveEnding).as_deref(),
+            Some("alt+f2")
+        );
+
+        assert!(Config::default().apply("keys: {submit: hyper+x}").is_err());
+        // ctrl+p is already prev_match's
+        assert!(Config::default().apply("keys: {copy: ctrl+p}").is_err());
+        assert!(Config::default().apply("colours: {game: plaid}").is_err());
+        assert!(Config::default().apply("padding: {vertical: 0.9}").is_err());
+        assert!(Config::default().apply("theme: dark").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use std::backtrace::Backtrace;
+use std::env;
+use std::fs;
+use std::io;
+use std::panic::{self, PanicHookInfo};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crossterm::{event::DisableMouseCapture, execute};
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+use crate::app::{App, Message};
+use crate::transcript::{self, Format};
+
+// where a game interrupted by a crash or a signal is saved, for `wwizard resume`
+pub const AUTOSAVE_PATH: &str = "wwizard-autosave.yaml";
+
+// how many of the latest messages a crash report includes
+const RECENT_MESSAGES: usize = 50;
+
+// what's worth keeping from the app if it panics, since the panic hook can't reach it
+struct Snapshot {
+    messages: Vec<Message>,
+    // None once there's nothing left to resume
+    save: Option<String>,
+}
+
+static SNAPSHOT: Mutex<Option<Snapshot>> = Mutex::new(None);
+static INTERRUPTED: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+// call after taking over the terminal: a panic puts it back before anything is
+// printed, and SIGINT or SIGTERM end the game loop instead of the process
+pub fn install() -> io::Result<()> {
+    for signal in [SIGINT, SIGTERM] {
+        signal_hook::flag::register(signal, Arc::clone(interrupted_flag()))?;
+    }
+
+    // ratatui::init's hook restores the terminal and prints the panic, so it runs first
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let _ = execute!(io::stdout(), DisableMouseCapture);
+        previous(info);
+        after_panic(info);
+    }));
+    Ok(())
+}
+
+// keep the latest messages and a save of the game, in case of a panic later on
+pub fn record(app: &App) {
+    let start = app.message_history.len().saturating_sub(RECENT_MESSAGES);
+    let over = app.exit || app.engine.game.ending.is_some();
+    let snapshot = Snapshot {
+        messages: app.message_history[start..].to_vec(),
+        save: if over { None } else { app.engine.save().ok() },
+    };
+    if let Ok(mut guard) = SNAPSHOT.lock() {
+        *guard = Some(snapshot);
+    }
+}
+
+// has SIGINT or SIGTERM arrived, or ctrl+c been pressed?
+pub fn interrupted() -> bool {
+    interrupted_flag().load(Ordering::Relaxed)
+}
+
+// what SIGINT does, for ctrl+c: the terminal is in raw mode, so it arrives as a key
+// instead of a signal
+pub fn interrupt() {
+    interrupted_flag().store(true, Ordering::Relaxed);
+}
+
+fn interrupted_flag() -> &'static Arc<AtomicBool> {
+    INTERRUPTED.get_or_init(Arc::default)
+}
+
+// save a game that was cut short, telling the player how to carry on
+pub fn autosave(save: &str) -> io::Result<()> {
+    fs::write(AUTOSAVE_PATH, save)?;
+    eprintln!(
+        "Your game was saved to {}. Carry on with `wwizard resume {}`.",
+        AUTOSAVE_PATH, AUTOSAVE_PATH
+    );
+    Ok(())
+}
+
+// write a crash report, and save the game if there's one to save. nothing is asked:
+// the panic may have come from any thread, and stdin is the game loop's
+fn after_panic(info: &PanicHookInfo) {
+    // try_lock: this thread may have panicked while recording
+    let snapshot = SNAPSHOT.try_lock().ok().and_then(|mut guard| guard.take());
+    let report = report(
+        &info.to_string(),
+        &Backtrace::force_capture().to_string(),
+        snapshot.as_ref(),
+    );
+
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = env::temp_dir().join(format!("wwizard-crash-{}.txt", seconds));
+    match fs::write(&path, report) {
+        Ok(()) => eprintln!("\nA crash report was written to {}.", path.display()),
+        Err(e) => eprintln!(
+            "\nCouldn't write a crash report to {}: {}",
+            path.display(),
+            e
+        ),
+    }
+
+    let Some(save) = snapshot.and_then(|snapshot| snapshot.save) else {
+        return;
+    };
+    if let Err(e) = autosave(&save) {
+        eprintln!("Couldn't save your game to {}: {}", AUTOSAVE_PATH, e);
+    }
+}
+
+// what went wrong, where, and what the player was doing at the time
+fn report(panic: &str, backtrace: &str, snapshot: Option<&Snapshot>) -> String {
+    let mut report = format!(
+        "wwizard {} crashed.\n\n{}\n\nbacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        panic,
+        backtrace
+    );
+    if let Some(snapshot) = snapshot {
+        report.push_str(&format!(
+            "\nthe last {} messages:\n\n{}",
+            snapshot.messages.len(),
+            transcript::render(&snapshot.messages, Format::Text)
+        ));
+        if let Some(save) = &snapshot.save {
+            report.push_str(&format!("\nthe game at the time:\n\n{}", save));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::MessageType;
+
+    #[test]
+    fn test_report() {
+        let snapshot = Snapshot {
+            messages: vec![
+                Message {
+                    text: "go east".into(),
+                    msg_type: MessageType::User,
+                },
+                Message {
+                    text: "A long hallway.".into(),
+                    msg_type: MessageType::narrator(),
+                },
+            ],
+            save: Some("game:\n  location: hallway\n".into()),
+        };
+        let crash = report("panicked at src/parser.rs:1:1", "0: main", Some(&snapshot));
+        assert!(crash.contains("panicked at src/parser.rs:1:1\n\nbacktrace:\n0: main\n"));
+        assert!(crash.contains("the last 2 messages:\n\n> go east\n\nA long hallway.\n"));
+        assert!(crash.ends_with("the game at the time:\n\ngame:\n  location: hallway\n"));
+
+        assert!(!report("panicked", "", None).contains("messages"));
+    }
+}
@@ -0,0 +1,47 @@
+use crate::game::Game;
+use crate::parser::{DialogueNode, DialogueOption, Npc};
+
+// where the player is in a conversation with an npc
+pub struct Conversation {
+    // index into the loaded npcs
+    pub npc: usize,
+    pub node: String,
+}
+
+impl Conversation {
+    pub fn start(npc: usize) -> Conversation {
+        Conversation {
+            npc,
+            node: "start".into(),
+        }
+    }
+
+    fn current_node<'a>(&self, npcs: &'a [Npc]) -> &'a DialogueNode {
+        npcs[self.npc]
+            .dialogue
+            .get(&self.node)
+            .unwrap_or_else(|| panic!("Dialogue node {} should exist.", self.node))
+    }
+
+    // the options at this node whose conditions currently hold, in menu order
+    pub fn options<'a>(&self, npcs: &'a [Npc], game: &Game) -> Vec<&'a DialogueOption> {
+        self.current_node(npcs)
+            .options
+            .iter()
+            .filter(|option| game.evaluate(&option.condition))
+            .collect()
+    }
+
+    // what the npc says, then a numbered menu of replies
+    pub fn render(&self, npcs: &[Npc], game: &Game) -> String {
+        let mut lines = vec![format!(
+            "{}: {}",
+            npcs[self.npc].name,
+            self.current_node(npcs).text
+        )];
+        for (i, option) in self.options(npcs, game).iter().enumerate() {
+            lines.push(format!("  {}. {}", i + 1, option.text));
+        }
+        lines.join("\n")
+    }
+}
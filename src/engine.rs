@@ -0,0 +1,1214 @@
+use std::io::{self, ErrorKind};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::cache::DescriptionCache;
+use crate::command::{split_commands, Command, NounPhrase, Resolution};
+use crate::dialogue::Conversation;
+use crate::events::Scheduler;
+use crate::game::Game;
+use crate::narrator::{Narrator, Prompt};
+use crate::parser::{
+    load_events, load_locations, load_npcs, load_voices, Action, Direction, Directive, Event, Hook,
+    Location, LocationId, Npc, Voice,
+};
+use crate::rng::Rng;
+use crate::verbs::{inventory_name, Verb};
+
+use yaml_rust2::{yaml::Hash, Yaml, YamlEmitter, YamlLoader};
+
+// where every new game begins
+pub const START_LOCATION: &str = "bedroom";
+
+// how many times hooks and events can move the player on from one arrival before the
+// engine gives up on them, e.g. two on_enter hooks that goto each other
+pub(crate) const MAX_CHAINED_MOVES: usize = 20;
+
+// everything a world's files say, loaded once. a world never changes during play,
+// so any number of games can share one
+pub struct World {
+    pub locations: Vec<Location>,
+    pub npcs: Vec<Npc>,
+    pub events: Vec<Event>,
+    pub voices: Vec<Voice>,
+    pub start: LocationId,
+}
+
+// the categories the engine's own messages come in. anything else is a speaker: an
+// npc, a player, or one of the world's voices
+pub const NARRATOR: &str = "narrator";
+pub const HINT: &str = "hint";
+pub const SYSTEM: &str = "system";
+pub const ERROR: &str = "error";
+
+// something the engine has to say to the player
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    // narration, and replies to what the player did
+    Text(String),
+    // something said by someone: an npc, or one of the world's voices
+    Speech { speaker: String, text: String },
+    // help for the player that isn't part of the story, like the ways out of a room
+    Hint(String),
+    // news about the game itself rather than the world, like a cache failing to save
+    System(String),
+    // something outside the world went wrong, like the narrator failing. already
+    // worded for the player, but a frontend may want to show it differently
+    Error(String),
+}
+
+// the result of trying an action
+#[derive(Default)]
+struct Outcome {
+    // set when the action's condition was false
+    fail: Option<String>,
+    directives: Vec<Directive>,
+    // key to mark a once-only action as done with
+    used: Option<String>,
+}
+
+// one game being played in a world. feed it what the player types with handle, and
+// show them whatever comes back. frontends own everything else (input, layout, quitting)
+pub struct Engine {
+    pub world: Arc<World>,
+    pub game: Game,
+    conversation: Option<Conversation>,
+    scheduler: Scheduler,
+    rng: Rng,
+    // the last command taken, for "again"
+    last_command: Option<String>,
+    // the last thing mentioned, for "it" and "them"
+    last_noun: Option<NounPhrase>,
+    narrator: Box<dyn Narrator>,
+    descriptions: DescriptionCache,
+    // what has been said since the last call to start, handle or regenerate
+    outputs: Vec<Output>,
+    // set while arriving somewhere, so that a hook's goto is arrived at afterwards
+    // rather than from inside the hook
+    arriving: bool,
+}
+
+impl World {
+    // locations in dir, and npcs, events and voices in dir/npcs, dir/events and dir/voices
+    pub fn load(dir: impl AsRef<Path>) -> World {
+        let dir = dir.as_ref();
+        World {
+            locations: load_locations(dir),
+            npcs: load_npcs(dir),
+            events: load_events(dir),
+            voices: load_voices(dir),
+            start: LocationId(START_LOCATION.into()),
+        }
+    }
+
+    pub fn location(&self, id: &LocationId) -> Option<&Location> {
+        self.locations
+            .iter()
+            .find(|location| location.title == id.0)
+    }
+
+    // how the world wants messages in this category drawn, if it says
+    pub fn style(&self, category: &str) -> Option<&str> {
+        self.voices
+            .iter()
+            .find(|voice| voice.name == category)
+            .and_then(|voice| voice.style.as_deref())
+    }
+
+    // the world's reply to input that's one of its asides, and the voice it's in
+    fn aside(&self, input: &str) -> Option<(&str, &str)> {
+        let input = input.trim().to_lowercase();
+        self.voices.iter().find_map(|voice| {
+            voice
+                .asides
+                .iter()
+                .find(|(aside, _)| *aside == input)
+                .map(|(_, text)| (voice.name.as_str(), text.as_str()))
+        })
+    }
+}
+
+impl Output {
+    pub fn text(&self) -> &str {
+        match self {
+            Output::Text(text)
+            | Output::Speech { text, .. }
+            | Output::Hint(text)
+            | Output::System(text)
+            | Output::Error(text) => text,
+        }
+    }
+
+    // what kind of message this is, for styling: one of the constants above, or the
+    // speaker's name
+    pub fn category(&self) -> &str {
+        match self {
+            Output::Text(_) => NARRATOR,
+            Output::Speech { speaker, .. } => speaker,
+            Output::Hint(_) => HINT,
+            Output::System(_) => SYSTEM,
+            Output::Error(_) => ERROR,
+        }
+    }
+}
+
+impl Engine {
+    pub fn new(
+        world: Arc<World>,
+        narrator: Box<dyn Narrator>,
+        descriptions: DescriptionCache,
+    ) -> Engine {
+        Engine {
+            game: Game::new(world.start.clone()),
+            scheduler: Scheduler::new(world.events.clone()),
+            world,
+            conversation: None,
+            rng: Rng::from_time(),
+            last_command: None,
+            last_noun: None,
+            narrator,
+            descriptions,
+            outputs: vec![],
+            arriving: false,
+        }
+    }
+
+    // describe the starting location and run anything due at the start. call once,
+    // before the first handle
+    pub fn start(&mut self) -> Vec<Output> {
+        self.arrive_until_settled();
+        let start = self.game.location.clone();
+        self.run_events(Some(&start));
+        self.take_outputs()
+    }
+
+    // everything needed to pick this game up again later, as yaml. conversations and
+    // "again" aren't saved; a restored game starts outside any conversation
+    pub fn save(&self) -> io::Result<String> {
+        let mut mapping = Hash::new();
+        mapping.insert(Yaml::String("game".into()), self.game.to_yaml());
+        mapping.insert(Yaml::String("events".into()), self.scheduler.to_yaml());
+
+        let mut out = String::new();
+        YamlEmitter::new(&mut out)
+            .dump(&Yaml::Hash(mapping))
+            .map_err(|e| io::Error::other(format!("{:?}", e)))?;
+        out.push('\n');
+        Ok(out)
+    }
+
+    // continue a saved game instead of starting a new one, and describe where the
+    // player is. call instead of start
+    pub fn restore(&mut self, save: &str) -> io::Result<Vec<Output>> {
+        let malformed = || io::Error::new(ErrorKind::InvalidData, "malformed save");
+        let docs = YamlLoader::load_from_str(save)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let doc = docs.first().ok_or_else(malformed)?;
+
+        let game = Game::from_yaml(&doc["game"]).ok_or_else(malformed)?;
+        self.scheduler.restore(&doc["events"]).ok_or_else(malformed)?;
+        self.game = game;
+        self.describe_location();
+        Ok(self.take_outputs())
+    }
+
+    // take one line of player input, which may be several commands
+    // ("take key then go north"), each of which is a turn
+    pub fn handle(&mut self, input: &str) -> Vec<Output> {
+        if let Some((speaker, text)) = self.world.aside(input) {
+            return vec![Output::Speech {
+                speaker: speaker.into(),
+                text: text.into(),
+            }];
+        }
+        for command in split_commands(input) {
+            // nothing left to do once the game is over
+            if self.game.ending.is_some() {
+                break;
+            }
+            let previous_location = self.game.location.clone();
+            self.take_turn(&command);
+            self.end_turn(&previous_location);
+        }
+        self.take_outputs()
+    }
+
+    // work out what the input means and carry it out. in order of precedence:
+    //      1) an enabled location action whose title parses to a matching command
+    //         (this is also how worlds override the built-in verbs)
+    //      2) a built-in verb
+    //      3) whichever action the narrator thinks the input was
+    fn take_turn(&mut self, input: &str) {
+        // mid-conversation, a number picks a reply. anything else walks away
+        if let Some(conversation) = self.conversation.take() {
+            if let Ok(choice) = input.trim().parse::<usize>() {
+                self.choose_option(conversation, choice);
+                return;
+            }
+        }
+
+        let Some(mut command) = Command::parse(input) else {
+            return;
+        };
+        if let Err(message) = self.resolve_nouns(&mut command) {
+            self.post_game_message(message);
+            return;
+        }
+
+        if let Some(outcome) = self.find_matching_action(&command) {
+            self.last_command = Some(input.into());
+            self.perform(outcome, input);
+            return;
+        }
+
+        match Verb::from_command(&command) {
+            // "again" isn't remembered itself, or it would only ever repeat itself
+            Some(Verb::Again) => self.again(),
+            Some(verb) => {
+                self.last_command = Some(input.into());
+                self.do_verb(verb);
+            }
+            None => {
+                self.last_command = Some(input.into());
+                self.narrate_action(input);
+            }
+        }
+    }
+
+    // advance the clock and let any due events happen
+    fn end_turn(&mut self, previous_location: &LocationId) {
+        if self.game.ending.is_some() {
+            return;
+        }
+        self.game.turn += 1;
+        let entered =
+            (self.game.location != *previous_location).then(|| self.game.location.clone());
+        self.run_events(entered.as_ref());
+    }
+
+    // an event that moves the player counts as entering the new location, which may
+    // set off more events
+    fn run_events(&mut self, entered: Option<&LocationId>) {
+        let mut entered = entered.cloned();
+        for _ in 0..MAX_CHAINED_MOVES {
+            let firings = self.scheduler.tick(&self.game, entered.as_ref());
+            let previous_location = self.game.location.clone();
+            for firing in firings {
+                if let Some(text) = firing.text {
+                    self.post_game_message(text);
+                }
+                self.apply(&firing.directives);
+            }
+            if self.game.location == previous_location {
+                return;
+            }
+            entered = Some(self.game.location.clone());
+        }
+    }
+
+    // swap pronouns for whatever was last mentioned, and name the scenery each noun
+    // phrase refers to. nouns that aren't scenery are left alone, since actions can
+    // still mention them ("recall information from that letter")
+    fn resolve_nouns(&mut self, command: &mut Command) -> Result<(), String> {
+        let objects = match self.world.location(&self.game.location) {
+            Some(location) => location.objects.as_slice(),
+            None => &[],
+        };
+
+        for phrase in [&mut command.object, &mut command.indirect]
+            .into_iter()
+            .flatten()
+        {
+            if phrase.is_pronoun() {
+                *phrase = self
+                    .last_noun
+                    .clone()
+                    .ok_or_else(|| format!("I'm not sure what you mean by \"{}\".", phrase.noun))?;
+            }
+
+            match phrase.resolve(objects) {
+                Resolution::Found(object) => *phrase = NounPhrase::new(&object.name),
+                Resolution::Ambiguous(objects) => {
+                    let names: Vec<String> = objects
+                        .iter()
+                        .map(|object| format!("the {}", object.name))
+                        .collect();
+                    return Err(format!("Which do you mean: {}?", names.join(" or ")));
+                }
+                Resolution::Unknown => (),
+            }
+            self.last_noun = Some(phrase.clone());
+        }
+        Ok(())
+    }
+
+    // whether an action in this location takes the input, e.g. a world's own
+    // "say password", so it isn't mistaken for something else
+    pub fn has_action_for(&self, input: &str) -> bool {
+        Command::parse(input).is_some_and(|command| self.find_matching_action(&command).is_some())
+    }
+
+    // the first action in this location that the command fits. enabled actions win;
+    // failing that, a disabled one that can at least tell the player why it didn't work
+    fn find_matching_action(&self, command: &Command) -> Option<Outcome> {
+        let location = self.world.location(&self.game.location)?;
+        let matching: Vec<&Action> = location
+            .actions
+            .iter()
+            .filter(|action| {
+                std::iter::once(&action.title)
+                    .chain(&action.aliases)
+                    .filter_map(|title| Command::parse(title))
+                    .any(|pattern| command.matches(&pattern))
+            })
+            .collect();
+        matching
+            .iter()
+            .find(|action| self.game.is_enabled(action))
+            .or_else(|| matching.iter().find(|action| action.has_feedback()))
+            .map(|action| self.outcome(action))
+    }
+
+    // what doing an action amounts to right now: its directives if its condition
+    // holds, otherwise its fail message and else directives
+    fn outcome(&self, action: &Action) -> Outcome {
+        if self.game.is_enabled(action) {
+            Outcome {
+                fail: None,
+                directives: action.directives.clone(),
+                used: action.once.then(|| self.game.action_key(action)),
+            }
+        } else {
+            Outcome {
+                fail: action.fail.clone(),
+                directives: action.else_directives.clone(),
+                used: None,
+            }
+        }
+    }
+
+    // ask the narrator which action the input was, then perform it
+    fn narrate_action(&mut self, input: &str) {
+        let Some(location) = self.world.location(&self.game.location) else {
+            self.post_game_message("You are nowhere at all. Something has gone very wrong.".into());
+            return;
+        };
+
+        // disabled actions are only worth offering if they have something to say,
+        // and hidden ones only work if the player names them
+        let actions: Vec<_> = location
+            .actions
+            .iter()
+            .filter(|action| !action.hidden)
+            .filter(|action| self.game.is_enabled(action) || action.has_feedback())
+            .collect();
+        let prompt = Prompt::Action {
+            actions: actions.iter().map(|action| action.title.clone()).collect(),
+            question: input.into(),
+        };
+        let outcome = match self.narrator.narrate(&prompt) {
+            Ok(response) => {
+                let response = response
+                    .trim()
+                    .trim_matches(|c| c == '"' || c == '\'' || c == '.');
+                actions
+                    .into_iter()
+                    .find(|action| action.title.eq_ignore_ascii_case(response))
+                    .map(|action| self.outcome(action))
+                    .unwrap_or_default()
+            }
+            Err(e) => {
+                self.post_narrator_error(e);
+                return;
+            }
+        };
+        self.perform(outcome, input);
+    }
+
+    // apply an action's directives, then describe the result
+    fn perform(&mut self, outcome: Outcome, input: &str) {
+        let failed = outcome.fail.is_some();
+        if let Some(fail) = outcome.fail {
+            self.post_game_message(fail);
+        }
+        if let Some(used) = outcome.used {
+            self.game.used_actions.insert(used);
+        }
+
+        // somewhere new: the room has already been described instead
+        if self.apply(&outcome.directives) {
+            return;
+        }
+        // the fail message already said what happened
+        if failed {
+            return;
+        }
+
+        let Some(location) = self.world.location(&self.game.location) else {
+            self.post_game_message("You step into the void. There is nothing here.".into());
+            return;
+        };
+        let prompt = Prompt::Scene {
+            location: location.title.clone(),
+            description: location.description.clone(),
+            items: String::new(),
+            question: input.into(),
+        };
+        match self.narrator.narrate(&prompt) {
+            Ok(response) => self.post_game_message(response),
+            Err(e) => self.post_narrator_error(e),
+        }
+    }
+
+    fn do_verb(&mut self, verb: Verb) {
+        match verb {
+            Verb::Go(direction) => self.go(direction),
+            Verb::Look => self.describe_location(),
+            Verb::Examine(thing) => self.examine(&thing),
+            Verb::Talk(npc) => self.talk_to(&npc),
+            Verb::Inventory => self.inventory(),
+            Verb::Help => self.help(),
+            Verb::Wait => self.post_game_message("Time passes.".into()),
+            Verb::Again => self.again(),
+        }
+    }
+
+    fn again(&mut self) {
+        match self.last_command.clone() {
+            Some(command) => self.take_turn(&command),
+            None => self.post_game_message("You haven't done anything yet.".into()),
+        }
+    }
+
+    fn examine(&mut self, thing: &NounPhrase) {
+        let description = self
+            .world
+            .location(&self.game.location)
+            .and_then(|location| match thing.resolve(&location.objects) {
+                Resolution::Found(object) => Some(object.description.clone()),
+                _ => None,
+            });
+        let description = description.or_else(|| {
+            self.find_npc(thing)
+                .map(|npc| self.world.npcs[npc].description.clone())
+        });
+        let text = description.unwrap_or_else(|| format!("You don't see any {} here.", thing));
+        self.post_game_message(text);
+    }
+
+    fn inventory(&mut self) {
+        let mut things: Vec<String> = self
+            .game
+            .properties
+            .iter()
+            .filter(|(_, value)| **value)
+            .filter_map(|(property_id, _)| inventory_name(property_id))
+            .collect();
+        things.sort();
+
+        let text = if things.is_empty() {
+            "You aren't carrying anything.".into()
+        } else {
+            format!("You are carrying: {}.", things.join(", "))
+        };
+        self.post_game_message(text);
+    }
+
+    // list what the player can do here
+    fn help(&mut self) {
+        let mut lines = vec![];
+        let actions = self.available_actions();
+        if !actions.is_empty() {
+            lines.push(format!("Things to do here: {}.", actions.join(", ")));
+        }
+        let directions = self.exit_directions();
+        if !directions.is_empty() {
+            lines.push(format!("Ways to go: {}.", directions.join(", ")));
+        }
+        lines.push(
+            "You can always look, examine <thing>, talk to <someone>, check your inventory, wait, or say again (g) to repeat yourself."
+                .into(),
+        );
+        self.outputs.push(Output::Hint(lines.join(" ")));
+    }
+
+    // apply directives in order. moving somewhere else runs the old location's on_exit
+    // hook on the way out and arrives at the new one. returns whether the player moved
+    fn apply(&mut self, directives: &[Directive]) -> bool {
+        let previous_location = self.game.location.clone();
+        self.apply_each(directives);
+
+        let moved = self.game.location != previous_location;
+        // a hook's goto only moves the player; the arrival it's part of carries on from there
+        if moved && !self.arriving {
+            self.arrive_until_settled();
+        }
+        moved
+    }
+
+    // arrive, and keep arriving for as long as hooks move the player on
+    fn arrive_until_settled(&mut self) {
+        self.arriving = true;
+        let mut settled = false;
+        for _ in 0..MAX_CHAINED_MOVES {
+            let here = self.game.location.clone();
+            self.arrive();
+            if self.game.location == here {
+                settled = true;
+                break;
+            }
+        }
+        self.arriving = false;
+
+        if !settled {
+            self.outputs.push(Output::Error(format!(
+                "[stopped at {} after {} moves in a row; its hooks seem to go round in a circle]",
+                self.game.location.0, MAX_CHAINED_MOVES
+            )));
+        }
+    }
+
+    fn apply_each(&mut self, directives: &[Directive]) {
+        for directive in directives {
+            match directive {
+                Directive::GoTo(target) if *target != self.game.location => {
+                    self.run_hook(|location| &location.on_exit);
+                    self.game.update(directive);
+                }
+                Directive::Say(text) => self.post_game_message(text.clone()),
+                Directive::Random(branches) => {
+                    let weights: Vec<u32> = branches.iter().map(|(weight, _)| *weight).collect();
+                    let branch = self.rng.pick_weighted(&weights);
+                    self.apply_each(&branches[branch].1);
+                }
+                Directive::Describe => self.describe_location(),
+                _ => self.game.update(directive),
+            }
+        }
+    }
+
+    // describe the new location and run its first_visit and on_enter hooks.
+    // visited_<location> is set after the first_visit hook, so it is false during it.
+    // a hook that moves the player on cuts the arrival short
+    fn arrive(&mut self) {
+        let here = self.game.location.clone();
+        let visited = format!("visited_{}", here.0);
+        let first_visit = !self.game.properties.get(&visited).unwrap_or(&false);
+
+        self.post_description();
+        if first_visit {
+            self.run_hook(|location| &location.first_visit);
+        }
+        self.game.properties.insert(visited, true);
+        if self.game.location != here {
+            return;
+        }
+        self.run_hook(|location| &location.on_enter);
+        if self.game.location != here {
+            return;
+        }
+        self.list_surroundings();
+    }
+
+    // post one of the current location's hooks, if it has it, and apply its directives
+    fn run_hook(&mut self, hook: impl Fn(&Location) -> &Option<Hook>) {
+        let Some(Hook { text, directives }) = self
+            .world
+            .location(&self.game.location)
+            .and_then(|location| hook(location).clone())
+        else {
+            return;
+        };
+        if let Some(text) = text {
+            self.post_game_message(text);
+        }
+        self.apply(&directives);
+    }
+
+    // post the description of wherever the player is, then who and what else is here
+    fn describe_location(&mut self) {
+        self.post_description();
+        self.list_surroundings();
+    }
+
+    fn list_surroundings(&mut self) {
+        self.list_npcs();
+        self.list_exits();
+    }
+
+    // post the (embellished) description of wherever the player is.
+    // embellishments are cached, so revisiting a room reads the same as last time
+    fn post_description(&mut self) {
+        let Some(location) = self.world.location(&self.game.location) else {
+            self.post_game_message("You step into the void. There is nothing here.".into());
+            return;
+        };
+        if let Some(text) = self.descriptions.get(location, &self.game) {
+            let text = text.clone();
+            self.post_game_message(text);
+            return;
+        }
+
+        let prompt = Prompt::Description {
+            description: location.description.clone(),
+        };
+        match self.narrator.narrate(&prompt) {
+            Ok(response) => {
+                if let Err(e) = self
+                    .descriptions
+                    .insert(location, &self.game, response.clone())
+                {
+                    self.outputs.push(Output::System(format!(
+                        "[couldn't save description cache: {}]",
+                        e
+                    )));
+                }
+                self.post_game_message(response);
+            }
+            Err(e) => self.post_narrator_error(e),
+        }
+    }
+
+    // follow the exit in the given direction, if there is one and it isn't locked
+    fn go(&mut self, direction: Direction) {
+        let Some(location) = self.world.location(&self.game.location) else {
+            self.post_game_message("You are nowhere at all. Something has gone very wrong.".into());
+            return;
+        };
+        let Some(exit) = location
+            .exits
+            .iter()
+            .find(|exit| exit.direction == direction)
+        else {
+            self.post_game_message("You can't go that way.".into());
+            return;
+        };
+        if !self.game.evaluate(&exit.condition) {
+            let message = exit
+                .locked_message
+                .clone()
+                .unwrap_or_else(|| format!("The way {} is blocked.", direction));
+            self.post_game_message(message);
+            return;
+        }
+
+        let target = exit.target.clone();
+        self.apply(&[Directive::GoTo(target)]);
+    }
+
+    // tell the player who else is here
+    fn list_npcs(&mut self) {
+        let here: Vec<String> = self
+            .world
+            .npcs
+            .iter()
+            .filter(|npc| npc.location == self.game.location)
+            .map(|npc| format!("{} is here.", npc.name))
+            .collect();
+        if !here.is_empty() {
+            self.post_game_message(here.join(" "));
+        }
+    }
+
+    // index of the npc in this location the phrase refers to
+    fn find_npc(&self, phrase: &NounPhrase) -> Option<usize> {
+        self.world.npcs.iter().position(|npc| {
+            npc.location == self.game.location && NounPhrase::new(&npc.name).matches(phrase)
+        })
+    }
+
+    fn talk_to(&mut self, phrase: &NounPhrase) {
+        match self.find_npc(phrase) {
+            Some(npc) => {
+                self.conversation = Some(Conversation::start(npc));
+                self.show_conversation();
+            }
+            None => self.post_game_message(format!("There's no one called {} here.", phrase)),
+        }
+    }
+
+    // post what the npc says and the numbered replies. a node with no replies left ends the conversation
+    fn show_conversation(&mut self) {
+        let Some(conversation) = &self.conversation else {
+            return;
+        };
+        let text = conversation.render(&self.world.npcs, &self.game);
+        let speaker = self.world.npcs[conversation.npc].name.clone();
+        if conversation
+            .options(&self.world.npcs, &self.game)
+            .is_empty()
+        {
+            self.conversation = None;
+        }
+        self.outputs.push(Output::Speech { speaker, text });
+    }
+
+    // pick a numbered reply in the current conversation
+    fn choose_option(&mut self, conversation: Conversation, choice: usize) {
+        let options = conversation.options(&self.world.npcs, &self.game);
+        let Some(option) = choice.checked_sub(1).and_then(|i| options.get(i)) else {
+            self.post_game_message("That isn't one of the options.".into());
+            self.conversation = Some(conversation);
+            return;
+        };
+        let directives = option.directives.clone();
+        let next = option.next.clone();
+
+        if self.apply(&directives) {
+            return;
+        }
+
+        match next {
+            Some(node) => {
+                self.conversation = Some(Conversation {
+                    npc: conversation.npc,
+                    node,
+                });
+                self.show_conversation();
+            }
+            None => {
+                let name = self.world.npcs[conversation.npc].name.clone();
+                self.post_game_message(format!("You end your conversation with {}.", name));
+            }
+        }
+    }
+
+    // tell the player which ways they can go from here
+    fn list_exits(&mut self) {
+        let directions = self.exit_directions();
+        if directions.is_empty() {
+            return;
+        }
+        self.outputs
+            .push(Output::Hint(format!("Exits: {}.", directions.join(", "))));
+    }
+
+    // the titles of the actions the player can see here right now
+    pub fn available_actions(&self) -> Vec<&str> {
+        let Some(location) = self.world.location(&self.game.location) else {
+            return vec![];
+        };
+        location
+            .actions
+            .iter()
+            .filter(|action| !action.hidden && self.game.is_enabled(action))
+            .map(|action| action.title.as_str())
+            .collect()
+    }
+
+    // the directions out of here, like "east"
+    pub fn exit_directions(&self) -> Vec<String> {
+        let Some(location) = self.world.location(&self.game.location) else {
+            return vec![];
+        };
+        location
+            .exits
+            .iter()
+            .map(|exit| exit.direction.to_string())
+            .collect()
+    }
+
+    // throw away the cached embellishment of this room and ask for a new one
+    pub fn regenerate(&mut self) -> Vec<Output> {
+        if let Some(location) = self.world.location(&self.game.location) {
+            if let Err(e) = self.descriptions.invalidate(location, &self.game) {
+                self.outputs.push(Output::System(format!(
+                    "[couldn't save description cache: {}]",
+                    e
+                )));
+            }
+        }
+        self.describe_location();
+        self.take_outputs()
+    }
+
+    fn post_game_message(&mut self, text: String) {
+        self.outputs.push(Output::Text(text));
+    }
+
+    fn post_narrator_error(&mut self, e: io::Error) {
+        self.outputs.push(Output::Error(format!(
+            "[the narrator is lost for words: {}]",
+            e
+        )));
+    }
+
+    fn take_outputs(&mut self) -> Vec<Output> {
+        std::mem::take(&mut self.outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Ending;
+    use crate::narrator::ReplayNarrator;
+    use crate::parser::{Condition, PropertyId, Trigger};
+
+    // an engine replaying the recorded bedroom session, and everything it has said
+    struct Player {
+        engine: Engine,
+        log: Vec<String>,
+    }
+
+    impl Player {
+        fn new(world: World) -> Player {
+            let narrator = ReplayNarrator::load("fixtures/bedroom_session.yaml").unwrap();
+            let mut engine = Engine::new(
+                Arc::new(world),
+                Box::new(narrator),
+                DescriptionCache::default(),
+            );
+            let outputs = engine.start();
+            let mut player = Player {
+                engine,
+                log: vec![],
+            };
+            player.record(outputs);
+            player
+        }
+
+        fn record(&mut self, outputs: Vec<Output>) {
+            self.log
+                .extend(outputs.iter().map(|output| output.text().to_string()));
+        }
+
+        fn submit(&mut self, input: &str) {
+            let outputs = self.engine.handle(input);
+            self.record(outputs);
+        }
+
+        fn apply(&mut self, directives: &[Directive]) {
+            self.engine.apply(directives);
+            let outputs = self.engine.take_outputs();
+            self.record(outputs);
+        }
+
+        fn last(&self) -> &str {
+            self.log.last().unwrap()
+        }
+
+        fn count(&self, text: &str) -> usize {
+            self.log.iter().filter(|line| *line == text).count()
+        }
+    }
+
+    #[test]
+    fn test_handle_returns_outputs() {
+        let world = Arc::new(World::load("assets"));
+        let narrator = ReplayNarrator::load("fixtures/bedroom_session.yaml").unwrap();
+        let mut engine = Engine::new(world, Box::new(narrator), DescriptionCache::default());
+
+        let intro = engine.start();
+        assert!(intro[0].text().starts_with("Soft pastel walls"));
+        assert_eq!(
+            engine.handle("north"),
+            vec![Output::Text("You can't go that way.".into())]
+        );
+        // nothing is said twice
+        assert_eq!(engine.handle(""), vec![]);
+    }
+
+    #[test]
+    fn test_exits() {
+        let mut player = Player::new(World::load("assets"));
+        assert_eq!(player.log[1], "Exits: east.");
+
+        player.submit("north");
+        assert_eq!(player.last(), "You can't go that way.");
+
+        player.submit("e");
+        assert_eq!(player.engine.game.location, LocationId("hallway".into()));
+        player.submit("walk west");
+        assert_eq!(player.engine.game.location, LocationId("bedroom".into()));
+    }
+
+    #[test]
+    fn test_location_hooks() {
+        let mut player = Player::new(World::load("assets"));
+        assert_eq!(
+            player.engine.game.properties.get("visited_bedroom"),
+            Some(&true)
+        );
+
+        let first_visit = "You've walked this hallway a thousand times, but today it feels longer, as if it has been waiting for you.";
+
+        player.submit("east");
+        assert_eq!(player.count("You pull the bedroom door to behind you."), 1);
+        assert_eq!(player.count(first_visit), 1);
+        assert_eq!(
+            player.engine.game.properties.get("visited_hallway"),
+            Some(&true)
+        );
+
+        player.submit("west then east");
+        assert_eq!(player.count("You pull the bedroom door to behind you."), 2);
+        assert_eq!(player.count(first_visit), 1);
+    }
+
+    #[test]
+    fn test_builtin_verbs() {
+        let mut player = Player::new(World::load("assets"));
+
+        player.submit("x the lamp");
+        assert!(player.last().contains("star"));
+        player.submit("examine unicorn");
+        assert_eq!(player.last(), "You don't see any unicorn here.");
+
+        player.submit("i");
+        assert_eq!(player.last(), "You aren't carrying anything.");
+        // word for word what help suggests
+        player.submit("check your inventory");
+        assert_eq!(player.last(), "You aren't carrying anything.");
+        player.engine.game.properties.insert("hasKey".into(), true);
+        player.submit("g");
+        assert_eq!(player.last(), "You are carrying: key.");
+
+        player.submit("help");
+        assert!(player.last().contains("read letter"));
+    }
+
+    #[test]
+    fn test_structured_commands() {
+        let mut player = Player::new(World::load("assets"));
+
+        // matches the "read letter" action without asking the narrator which one it was
+        player.submit("x the letter then read it");
+        assert_eq!(
+            player.engine.game.properties.get("didReadLetter"),
+            Some(&true)
+        );
+        assert!(player.log.iter().any(|m| m.contains("blue wax")));
+        assert!(player.last().starts_with("You break the blue wax seal"));
+
+        player.submit("examine them");
+        assert!(player.last().contains("blue wax"));
+    }
+
+    #[test]
+    fn test_dialogue() {
+        let mut player = Player::new(World::load("assets"));
+        player.engine.game.location = LocationId("hallway".into());
+
+        player.submit("talk to marigold");
+        let menu = player.last().to_string();
+        assert!(menu.starts_with("Marigold: "));
+        assert!(menu.contains("1. Who are you?"));
+        // gated on !didReadLetter
+        assert!(menu.contains("What's in the letter"));
+        assert!(!menu.contains("What does it mean?"));
+
+        player.submit("7");
+        assert_eq!(player.last(), "That isn't one of the options.");
+        player.submit("1");
+        assert!(player.last().contains("longer than the house"));
+        player.submit("1");
+        player.submit("3");
+        assert_eq!(player.last(), "You end your conversation with Marigold.");
+
+        player
+            .engine
+            .game
+            .properties
+            .insert("didReadLetter".into(), true);
+        player.submit("talk to her");
+        player.submit("2");
+        assert_eq!(
+            player
+                .engine
+                .game
+                .properties
+                .get("askedMarigoldAboutLetter"),
+            Some(&true)
+        );
+        assert!(player.last().contains("biscuit"));
+    }
+
+    #[test]
+    fn test_events() {
+        let mut player = Player::new(World::load("assets"));
+        assert_eq!(player.engine.game.turn, 0);
+
+        player.submit("x letter then read it");
+        assert_eq!(player.engine.game.turn, 2);
+
+        // the lamp goes 3 turns after the letter is read
+        player.submit("wait then wait");
+        assert_eq!(player.engine.game.properties.get("lampFlickered"), None);
+        player.submit("z");
+        assert_eq!(
+            player.engine.game.properties.get("lampFlickered"),
+            Some(&true)
+        );
+        assert!(player.last().contains("flickers"));
+    }
+
+    #[test]
+    fn test_event_move_enters_location() {
+        let mut world = World::load("assets");
+        world.events.push(Event {
+            id: "pushed".into(),
+            trigger: Trigger::When(Condition::IsPropertyTrue(PropertyId("pushed".into()))),
+            after: 0,
+            text: None,
+            directives: vec![Directive::GoTo(LocationId("hallway".into()))],
+            repeat: false,
+        });
+        world.events.push(Event {
+            id: "draught".into(),
+            trigger: Trigger::Enter(LocationId("hallway".into())),
+            after: 0,
+            text: Some("A draught follows you in.".into()),
+            directives: vec![],
+            repeat: false,
+        });
+        let mut player = Player::new(world);
+
+        player.engine.game.properties.insert("pushed".into(), true);
+        player.submit("wait");
+        assert_eq!(player.engine.game.location, LocationId("hallway".into()));
+        assert_eq!(
+            player.engine.game.properties.get("visited_hallway"),
+            Some(&true)
+        );
+        // being pushed in counts as walking in
+        assert_eq!(player.count("A draught follows you in."), 1);
+    }
+
+    #[test]
+    fn test_circular_hooks() {
+        let mut world = World::load("assets");
+        for (from, to) in [("bedroom", "hallway"), ("hallway", "bedroom")] {
+            let location = world
+                .locations
+                .iter_mut()
+                .find(|location| location.title == from)
+                .unwrap();
+            location.on_enter = Some(Hook {
+                text: None,
+                directives: vec![Directive::GoTo(LocationId(to.into()))],
+            });
+        }
+
+        // gives up rather than going round forever
+        let player = Player::new(world);
+        assert!(player.last().contains("go round in a circle"));
+    }
+
+    #[test]
+    fn test_effect_directives() {
+        let mut player = Player::new(World::load("assets"));
+        player.engine.rng = Rng::seeded(7);
+
+        player.apply(&[
+            Directive::Say("The lamp hums.".into()),
+            Directive::Toggle(PropertyId("lampOn".into())),
+            Directive::Random(vec![
+                (1, vec![Directive::Say("Heads.".into())]),
+                (0, vec![Directive::Say("Tails.".into())]),
+            ]),
+        ]);
+        let n = player.log.len();
+        assert_eq!(player.log[n - 2], "The lamp hums.");
+        assert_eq!(player.log[n - 1], "Heads.");
+        assert_eq!(player.engine.game.properties.get("lampOn"), Some(&true));
+
+        player.apply(&[Directive::Describe]);
+        assert_eq!(player.last(), "Exits: east.");
+
+        player.apply(&[Directive::Lose(Some("You were eaten by a grue.".into()))]);
+        assert_eq!(
+            player.engine.game.ending,
+            Some(Ending::Lost(Some("You were eaten by a grue.".into())))
+        );
+        let n = player.log.len();
+        player.submit("look");
+        assert_eq!(player.log.len(), n);
+    }
+
+    #[test]
+    fn test_fail_message() {
+        let mut player = Player::new(World::load("assets"));
+        player.submit("east");
+
+        let n = player.log.len();
+        player.submit("recall information from the letter");
+        assert_eq!(player.log.len(), n + 1);
+        assert_eq!(
+            player.last(),
+            "You try to recall the letter, but you haven't actually read it yet."
+        );
+        assert_eq!(player.engine.game.location, LocationId("hallway".into()));
+    }
+
+    #[test]
+    fn test_world_overrides_verb() {
+        let mut world = World::load("assets");
+        world.locations[0].actions.push(Action {
+            title: "look".into(),
+            condition: Condition::Always,
+            directives: vec![Directive::GoTo(LocationId("attic".into()))],
+            else_directives: vec![],
+            fail: None,
+            aliases: vec![],
+            hidden: false,
+            once: false,
+        });
+        let start = LocationId(world.locations[0].title.clone());
+        let mut player = Player::new(world);
+        player.engine.game.location = start;
+
+        // "l" is "look", which this location has taken over
+        player.submit("l");
+        assert_eq!(player.engine.game.location, LocationId("attic".into()));
+    }
+
+    #[test]
+    fn test_hidden_once_action() {
+        let mut world = World::load("assets");
+        world.locations[0].actions.push(Action {
+            title: "turn ring".into(),
+            aliases: vec!["twist ring".into()],
+            condition: Condition::Always,
+            directives: vec![Directive::Say("The ring turns with a click.".into())],
+            else_directives: vec![],
+            fail: None,
+            hidden: true,
+            once: true,
+        });
+        let start = LocationId(world.locations[0].title.clone());
+        let mut player = Player::new(world);
+        player.engine.game.location = start;
+
+        player.submit("help");
+        assert!(!player.last().contains("turn ring"));
+
+        player.submit("twist the ring");
+        assert_eq!(player.count("The ring turns with a click."), 1);
+
+        // done once, so the same command no longer reaches the action
+        player.submit("turn ring");
+        assert_eq!(player.count("The ring turns with a click."), 1);
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let mut player = Player::new(World::load("assets"));
+        player.submit("skim through my mail");
+        player.submit("go east");
+        let save = player.engine.save().unwrap();
+
+        let mut restored = Player::new(World::load("assets"));
+        let outputs = restored.engine.restore(&save).unwrap();
+        assert_eq!(outputs.last().unwrap().text(), "Exits: west.");
+        assert_eq!(restored.engine.game.location, LocationId("hallway".into()));
+        assert_eq!(restored.engine.game.turn, player.engine.game.turn);
+        assert_eq!(
+            restored.engine.game.properties,
+            player.engine.game.properties
+        );
+        assert_eq!(restored.engine.save().unwrap(), save);
+
+        assert!(restored.engine.restore("game: {}").is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use std::collections::{HashMap, HashSet};
+
+use yaml_rust2::{yaml::Hash, Yaml};
+
+use crate::game::{string_set_from_yaml, string_set_to_yaml, Game};
+use crate::parser::{Directive, Event, LocationId, Trigger};
+
+// what a fired event does: an optional message, then its directives
+pub struct Firing {
+    pub text: Option<String>,
+    pub directives: Vec<Directive>,
+}
+
+// decides which events fire at the end of each turn.
+//
+// an event is armed when its trigger happens (the game starting, the player entering
+// a location, or a condition turning true) and fires `after` turns later
+pub struct Scheduler {
+    events: Vec<Event>,
+    // event id -> turn it was armed on
+    armed: HashMap<String, u32>,
+    // ids of `when` events whose condition held at the end of the last turn
+    was_true: HashSet<String>,
+    // ids of one-off events that have already fired
+    fired: HashSet<String>,
+}
+
+impl Scheduler {
+    pub fn new(events: Vec<Event>) -> Scheduler {
+        let armed = events
+            .iter()
+            .filter(|event| matches!(event.trigger, Trigger::Start))
+            .map(|event| (event.id.clone(), 0))
+            .collect();
+        Scheduler {
+            events,
+            armed,
+            was_true: HashSet::new(),
+            fired: HashSet::new(),
+        }
+    }
+
+    // call at the end of every turn, after game.turn has been advanced.
+    // entered is the location the player walked into this turn, if any
+    pub fn tick(&mut self, game: &Game, entered: Option<&LocationId>) -> Vec<Firing> {
+        for event in &self.events {
+            if self.fired.contains(&event.id) {
+                continue;
+            }
+            let triggered = match &event.trigger {
+                Trigger::Start => false,
+                Trigger::Enter(location) => entered == Some(location),
+                Trigger::When(condition) => {
+                    let is_true = game.evaluate(condition);
+                    let became_true = is_true && !self.was_true.contains(&event.id);
+                    if is_true {
+                        self.was_true.insert(event.id.clone());
+                    } else {
+                        self.was_true.remove(&event.id);
+                    }
+                    became_true
+                }
+            };
+            // re-triggering a pending event doesn't restart its timer
+            if triggered && !self.armed.contains_key(&event.id) {
+                self.armed.insert(event.id.clone(), game.turn);
+            }
+        }
+
+        let mut firings = vec![];
+        for event in &self.events {
+            let due = self
+                .armed
+                .get(&event.id)
+                .is_some_and(|armed_on| game.turn >= armed_on + event.after);
+            if !due {
+                continue;
+            }
+
+            self.armed.remove(&event.id);
+            if !event.repeat {
+                self.fired.insert(event.id.clone());
+            }
+            firings.push(Firing {
+                text: event.text.clone(),
+                directives: event.directives.clone(),
+            });
+        }
+        firings
+    }
+
+    // which events are pending or done, as part of a save file
+    pub fn to_yaml(&self) -> Yaml {
+        let mut armed: Vec<(&String, &u32)> = self.armed.iter().collect();
+        armed.sort();
+
+        let mut mapping = Hash::new();
+        mapping.insert(
+            Yaml::String("armed".into()),
+            Yaml::Hash(
+                armed
+                    .into_iter()
+                    .map(|(id, turn)| (Yaml::String(id.clone()), Yaml::Integer(*turn as i64)))
+                    .collect(),
+            ),
+        );
+        mapping.insert(
+            Yaml::String("was_true".into()),
+            string_set_to_yaml(&self.was_true),
+        );
+        mapping.insert(
+            Yaml::String("fired".into()),
+            string_set_to_yaml(&self.fired),
+        );
+        Yaml::Hash(mapping)
+    }
+
+    // pick up where a saved scheduler left off; None if the yaml is malformed.
+    // ids of events the world no longer has are harmless and just never match
+    pub fn restore(&mut self, yaml: &Yaml) -> Option<()> {
+        let armed = yaml["armed"]
+            .as_hash()?
+            .iter()
+            .map(|(id, turn)| {
+                Some((
+                    id.as_str()?.to_string(),
+                    u32::try_from(turn.as_i64()?).ok()?,
+                ))
+            })
+            .collect::<Option<_>>()?;
+        self.was_true = string_set_from_yaml(&yaml["was_true"])?;
+        self.fired = string_set_from_yaml(&yaml["fired"])?;
+        self.armed = armed;
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{Condition, PropertyId};
+
+    fn event(id: &str, trigger: Trigger, after: u32, repeat: bool) -> Event {
+        Event {
+            id: id.into(),
+            trigger,
+            after,
+            text: Some(id.into()),
+            directives: vec![],
+            repeat,
+        }
+    }
+
+    fn fired(scheduler: &mut Scheduler, game: &Game, entered: Option<&LocationId>) -> Vec<String> {
+        scheduler
+            .tick(game, entered)
+            .into_iter()
+            .filter_map(|firing| firing.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_timed_event() {
+        let mut scheduler = Scheduler::new(vec![event("bells", Trigger::Start, 2, false)]);
+        let mut game = Game::new(LocationId("bedroom".into()));
+
+        game.turn = 1;
+        assert!(fired(&mut scheduler, &game, None).is_empty());
+        game.turn = 2;
+        assert_eq!(fired(&mut scheduler, &game, None), vec!["bells"]);
+        game.turn = 3;
+        assert!(fired(&mut scheduler, &game, None).is_empty());
+    }
+
+    #[test]
+    fn test_when_event_fires_on_edge() {
+        let condition = Condition::IsPropertyTrue(PropertyId("candleLit".into()));
+        let mut scheduler = Scheduler::new(vec![event(
+            "candle burns out",
+            Trigger::When(condition),
+            1,
+            true,
+        )]);
+        let mut game = Game::new(LocationId("bedroom".into()));
+
+        game.turn = 1;
+        game.properties.insert("candleLit".into(), true);
+        assert!(fired(&mut scheduler, &game, None).is_empty());
+        game.turn = 2;
+        assert_eq!(fired(&mut scheduler, &game, None), vec!["candle burns out"]);
+
+        // still lit, so no new edge
+        game.turn = 3;
+        assert!(fired(&mut scheduler, &game, None).is_empty());
+
+        // relit
+        game.properties.insert("candleLit".into(), false);
+        game.turn = 4;
+        assert!(fired(&mut scheduler, &game, None).is_empty());
+        game.properties.insert("candleLit".into(), true);
+        game.turn = 5;
+        fired(&mut scheduler, &game, None);
+        game.turn = 6;
+        assert_eq!(fired(&mut scheduler, &game, None), vec!["candle burns out"]);
+    }
+
+    #[test]
+    fn test_enter_event_fires_once() {
+        let hallway = LocationId("hallway".into());
+        let mut scheduler = Scheduler::new(vec![event(
+            "draught",
+            Trigger::Enter(hallway.clone()),
+            0,
+            false,
+        )]);
+        let mut game = Game::new(hallway.clone());
+
+        game.turn = 1;
+        assert_eq!(fired(&mut scheduler, &game, Some(&hallway)), vec!["draught"]);
+        game.turn = 2;
+        assert!(fired(&mut scheduler, &game, Some(&hallway)).is_empty());
+    }
+}
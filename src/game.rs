@@ -1,34 +1,166 @@
+use crate::parser::Action;
 use crate::parser::Condition;
 use crate::parser::Directive;
 use crate::parser::PropertyId;
 
 use super::parser::LocationId;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use yaml_rust2::{yaml::Hash, Yaml};
 
+#[derive(Clone)]
 pub struct Game {
     pub location: LocationId,
     pub properties: HashMap<String, bool>,
+    // turns taken so far
+    pub turn: u32,
+    // set once a win or lose directive has ended the game
+    pub ending: Option<Ending>,
+    // "<location>:<title>" of once-only actions that have been done
+    pub used_actions: HashSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ending {
+    Won(Option<String>),
+    Lost(Option<String>),
 }
 
 impl Game {
-    fn evaluate(&self, cond: Condition) -> bool {
+    pub fn new(location: LocationId) -> Game {
+        Game {
+            location,
+            properties: HashMap::new(),
+            turn: 0,
+            ending: None,
+            used_actions: HashSet::new(),
+        }
+    }
+
+    // can the player do this action, in the current location, right now?
+    pub fn is_enabled(&self, action: &Action) -> bool {
+        self.evaluate(&action.condition)
+            && !(action.once && self.used_actions.contains(&self.action_key(action)))
+    }
+
+    pub fn action_key(&self, action: &Action) -> String {
+        format!("{}:{}", self.location.0, action.title)
+    }
+
+    // the game as part of a save file
+    pub fn to_yaml(&self) -> Yaml {
+        let mut properties: Vec<(&String, &bool)> = self.properties.iter().collect();
+        properties.sort();
+
+        let mut mapping = Hash::new();
+        mapping.insert(key("location"), Yaml::String(self.location.0.clone()));
+        mapping.insert(key("turn"), Yaml::Integer(self.turn as i64));
+        mapping.insert(
+            key("properties"),
+            Yaml::Hash(
+                properties
+                    .into_iter()
+                    .map(|(property_id, value)| {
+                        (Yaml::String(property_id.clone()), Yaml::Boolean(*value))
+                    })
+                    .collect(),
+            ),
+        );
+        mapping.insert(key("used_actions"), string_set_to_yaml(&self.used_actions));
+        if let Some(ending) = &self.ending {
+            let (won, message) = match ending {
+                Ending::Won(message) => (true, message),
+                Ending::Lost(message) => (false, message),
+            };
+            let mut yaml_ending = Hash::new();
+            yaml_ending.insert(key("won"), Yaml::Boolean(won));
+            if let Some(message) = message {
+                yaml_ending.insert(key("message"), Yaml::String(message.clone()));
+            }
+            mapping.insert(key("ending"), Yaml::Hash(yaml_ending));
+        }
+        Yaml::Hash(mapping)
+    }
+
+    // the reverse of to_yaml; None if anything is missing or the wrong type
+    pub fn from_yaml(yaml: &Yaml) -> Option<Game> {
+        let properties = yaml["properties"]
+            .as_hash()?
+            .iter()
+            .map(|(property_id, value)| Some((property_id.as_str()?.to_string(), value.as_bool()?)))
+            .collect::<Option<_>>()?;
+        let ending = match &yaml["ending"] {
+            Yaml::BadValue => None,
+            yaml_ending => {
+                let message = match &yaml_ending["message"] {
+                    Yaml::BadValue => None,
+                    message => Some(message.as_str()?.to_string()),
+                };
+                Some(match yaml_ending["won"].as_bool()? {
+                    true => Ending::Won(message),
+                    false => Ending::Lost(message),
+                })
+            }
+        };
+        Some(Game {
+            location: LocationId(yaml["location"].as_str()?.to_string()),
+            properties,
+            turn: u32::try_from(yaml["turn"].as_i64()?).ok()?,
+            ending,
+            used_actions: string_set_from_yaml(&yaml["used_actions"])?,
+        })
+    }
+
+    pub fn evaluate(&self, cond: &Condition) -> bool {
         match cond {
+            Condition::Always => true,
             Condition::IsPropertyTrue(PropertyId(property_id)) => {
-                *(self.properties.get(&property_id).unwrap_or(&false))
+                *(self.properties.get(property_id).unwrap_or(&false))
             }
-            Condition::Not(b) => self.evaluate(*b),
-            Condition::Or(b1, b2) => self.evaluate(*b1) || self.evaluate(*b2),
-            Condition::And(b1, b2) => self.evaluate(*b1) && self.evaluate(*b2),
+            Condition::Not(b) => !self.evaluate(b),
+            Condition::Or(b1, b2) => self.evaluate(b1) || self.evaluate(b2),
+            Condition::And(b1, b2) => self.evaluate(b1) && self.evaluate(b2),
         }
     }
 
-    fn update(&mut self, directive: Directive) {
+    pub fn update(&mut self, directive: &Directive) {
         match directive {
-            Directive::GoTo(new_location) => self.location = new_location,
+            Directive::GoTo(new_location) => self.location = new_location.clone(),
             Directive::SetProperty(PropertyId(property_id), value) => {
-                let prop = self.properties.entry(property_id).or_insert(false);
-                *prop = value
+                let prop = self.properties.entry(property_id.clone()).or_insert(false);
+                *prop = *value
+            }
+            Directive::Toggle(PropertyId(property_id)) => {
+                let prop = self.properties.entry(property_id.clone()).or_insert(false);
+                *prop = !*prop
             }
+            Directive::Win(message) => self.ending = Some(Ending::Won(message.clone())),
+            Directive::Lose(message) => self.ending = Some(Ending::Lost(message.clone())),
+            // these only affect what the player sees, or need a source of randomness;
+            // the frontend handles them
+            Directive::Say(_) | Directive::Random(_) | Directive::Describe => (),
         }
     }
 }
+
+fn key(name: &str) -> Yaml {
+    Yaml::String(name.into())
+}
+
+// sorted, so save files diff nicely
+pub(crate) fn string_set_to_yaml(set: &HashSet<String>) -> Yaml {
+    let mut strings: Vec<&String> = set.iter().collect();
+    strings.sort();
+    Yaml::Array(
+        strings
+            .into_iter()
+            .map(|s| Yaml::String(s.clone()))
+            .collect(),
+    )
+}
+
+pub(crate) fn string_set_from_yaml(yaml: &Yaml) -> Option<HashSet<String>> {
+    yaml.as_vec()?
+        .iter()
+        .map(|s| Some(s.as_str()?.to_string()))
+        .collect()
+}
@@ -0,0 +1,21 @@
+// the wwizard engine: worlds loaded from YAML, and games played in them.
+// the terminal app in main.rs is one frontend; anything else can embed an Engine the same way
+
+pub mod cache;
+pub mod check;
+pub mod command;
+pub mod dialogue;
+pub mod engine;
+pub mod events;
+pub mod game;
+pub mod map;
+pub mod multiplayer;
+pub mod narrator;
+pub mod parser;
+pub mod rng;
+pub mod solver;
+pub mod verbs;
+
+pub use engine::{Engine, Output, World};
+pub use game::{Ending, Game};
+pub use parser::{Action, Condition, Directive, Location};
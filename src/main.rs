@@ -1,41 +1,191 @@
+use std::fs;
 use std::io;
+use std::sync::Arc;
 
 mod app;
-mod game;
-mod parser;
+mod clipboard;
+mod config;
+mod crash;
+mod serve;
+mod sessions;
+mod telnet;
+mod transcript;
 mod ui;
 
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+};
 use ratatui::DefaultTerminal;
 
+use wwizard::{cache, check, map, narrator, solver, Engine, World};
+
 use crate::{app::App, ui::ui};
 
+// where the world's files are
+const WORLD_DIR: &str = "assets";
+// where `serve` listens unless told otherwise
+const SERVE_ADDR: &str = "127.0.0.1:8080";
+// and `telnet`
+const TELNET_ADDR: &str = "127.0.0.1:4000";
+
+const USAGE: &str = "usage: wwizard [resume save | schema [location|npc|events|voice] | check [dir] | export-map [dot|mermaid] | solve | serve [--shared] [addr] | telnet [addr]]";
+
 fn main() -> io::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => play(None),
+        ["resume", path] => play(Some(path)),
+        ["schema"] => {
+            print!("{}", check::schema(check::WorldFile::Location));
+            Ok(())
+        }
+        ["schema", file] => match check::WorldFile::from_name(file) {
+            Some(file) => {
+                print!("{}", check::schema(file));
+                Ok(())
+            }
+            None => {
+                eprintln!(
+                    "unknown kind of world file {}; expected location, npc, events or voice",
+                    file
+                );
+                std::process::exit(2);
+            }
+        },
+        ["check"] => check_world(WORLD_DIR),
+        ["check", dir] => check_world(dir),
+        ["solve"] => {
+            let report = solver::solve(&World::load(WORLD_DIR));
+            print!("{}", report);
+            Ok(())
+        }
+        ["serve"] => serve::serve(SERVE_ADDR, Arc::new(World::load(WORLD_DIR)), false),
+        ["serve", "--shared"] => serve::serve(SERVE_ADDR, Arc::new(World::load(WORLD_DIR)), true),
+        ["serve", "--shared", addr] => serve::serve(addr, Arc::new(World::load(WORLD_DIR)), true),
+        ["serve", addr] => serve::serve(addr, Arc::new(World::load(WORLD_DIR)), false),
+        ["telnet"] => telnet::serve(TELNET_ADDR, Arc::new(World::load(WORLD_DIR))),
+        ["telnet", addr] => telnet::serve(addr, Arc::new(World::load(WORLD_DIR))),
+        ["export-map"] => export_map(map::Format::Dot),
+        ["export-map", format] => match map::Format::from_name(format) {
+            Some(format) => export_map(format),
+            None => {
+                eprintln!("unknown map format {}; expected dot or mermaid", format);
+                std::process::exit(2);
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+// print what's wrong with a world, compiler style, and fail if any of it is an error
+fn check_world(dir: &str) -> io::Result<()> {
+    let diagnostics = check::check_world(dir);
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == check::Severity::Error)
+        .count();
+    eprintln!(
+        "{} error(s), {} warning(s)",
+        errors,
+        diagnostics.len() - errors
+    );
+    if errors > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn export_map(format: map::Format) -> io::Result<()> {
+    print!("{}", map::export(&World::load(WORLD_DIR).locations, format));
+    Ok(())
+}
+
+// play in the terminal: a new game, or carry on from a save
+fn play(save: Option<&str>) -> io::Result<()> {
+    // pick the AI backend before taking over the terminal so errors print normally
+    let narrator = narrator::from_env()?;
+    let descriptions = cache::DescriptionCache::from_env()?;
+    let mut config = config::Config::from_env()?;
+    let world = Arc::new(World::load(WORLD_DIR));
+    config.theme.add_voices(&world);
+    let save = save.map(fs::read_to_string).transpose()?;
+
     // initialize new terminal in the alternate screen buffer
     let mut terminal = ratatui::init();
     terminal.clear()?;
+    // for the scroll wheel, and clicking exits, actions and the input box
+    execute!(io::stdout(), EnableMouseCapture)?;
+    // and put it all back on a panic, SIGINT or SIGTERM
+    crash::install()?;
 
     // create the app state, start program loop
-    let mut app = App::new();
-    let res = run_app(&mut terminal, &mut app);
+    let res = start_app(world, narrator, descriptions, save).and_then(|mut app| {
+        app.config = config;
+        let res = run_app(&mut terminal, &mut app);
+        // cut short by a signal or ctrl+c: keep the game so it can be resumed
+        if res.is_ok() && crash::interrupted() && !app.exit && app.engine.game.ending.is_none() {
+            return Ok(Some(app.engine.save()?));
+        }
+        res.map(|()| None)
+    });
 
-    // restore the terminal's original state
+    // restore the terminal's original state, whatever happened
+    let _ = execute!(io::stdout(), DisableMouseCapture);
     ratatui::restore();
 
-    res
+    match res? {
+        Some(save) => crash::autosave(&save),
+        None => Ok(()),
+    }
+}
+
+fn start_app(
+    world: Arc<World>,
+    narrator: Box<dyn narrator::Narrator>,
+    descriptions: cache::DescriptionCache,
+    save: Option<String>,
+) -> io::Result<App> {
+    let mut engine = Engine::new(world, narrator, descriptions);
+    match save {
+        Some(save) => {
+            let outputs = engine.restore(&save)?;
+            Ok(App::with_outputs(engine, outputs))
+        }
+        None => Ok(App::new(engine)),
+    }
 }
 
 fn run_app(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+    // kept in case of a panic
+    crash::record(app);
+
     // run until quit
     // two steps to the application loop
     //      1)  redraw the frame
     //      2)  handle events, if any
-    while app.exit == false {
+    while !app.exit && !crash::interrupted() {
         // ui handled in separate module
         terminal.draw(|frame| ui(frame, app))?;
         // state updates handled in app state
+        let seen = app.message_history.len();
         app.handle_terminal_events()?;
+        // the game only changes when something is said, so only then is it kept again
+        if app.message_history.len() != seen {
+            crash::record(app);
+        }
     }
 
     Ok(())
 }
-
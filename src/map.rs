@@ -0,0 +1,237 @@
+use std::collections::BTreeSet;
+
+use crate::parser::{Condition, Directive, Location};
+
+// how to draw the world
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    // graphviz, e.g. `wwizard export-map dot | dot -Tsvg > map.svg`
+    Dot,
+    // pastes straight into a markdown file on most forges
+    Mermaid,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "dot" | "graphviz" => Some(Format::Dot),
+            "mermaid" => Some(Format::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+// one way of getting from one location to another
+#[derive(Debug, PartialEq)]
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    // the action title, exit direction or hook that moves the player
+    pub label: String,
+}
+
+// every edge in the world: exits, and goto directives in actions and hooks.
+// a goto inside a random branch still counts, since it can happen
+pub fn edges(locations: &[Location]) -> Vec<Edge> {
+    let mut edges = vec![];
+    for location in locations {
+        let mut add = |to: &str, label: String| {
+            edges.push(Edge {
+                from: location.title.clone(),
+                to: to.into(),
+                label,
+            })
+        };
+
+        for exit in &location.exits {
+            add(
+                &exit.target.0,
+                with_condition(exit.direction.to_string(), &exit.condition),
+            );
+        }
+        for action in &location.actions {
+            for target in goto_targets(&action.directives) {
+                add(
+                    &target,
+                    with_condition(action.title.clone(), &action.condition),
+                );
+            }
+            // else directives run when the condition is false
+            for target in goto_targets(&action.else_directives) {
+                add(
+                    &target,
+                    with_condition(
+                        action.title.clone(),
+                        &Condition::Not(Box::new(action.condition.clone())),
+                    ),
+                );
+            }
+        }
+        let hooks = [
+            ("on_enter", &location.on_enter),
+            ("on_exit", &location.on_exit),
+            ("first_visit", &location.first_visit),
+        ];
+        for (name, hook) in hooks {
+            if let Some(hook) = hook {
+                for target in goto_targets(&hook.directives) {
+                    add(&target, name.into());
+                }
+            }
+        }
+    }
+    edges
+}
+
+// the world as a graph, with locations that are pointed to but don't exist drawn in red
+pub fn export(locations: &[Location], format: Format) -> String {
+    let known: BTreeSet<&str> = locations
+        .iter()
+        .map(|location| location.title.as_str())
+        .collect();
+
+    // locations are loaded in directory order; sort so the output is stable
+    let mut edges = edges(locations);
+    edges.sort_by(|a, b| a.from.cmp(&b.from));
+    let dangling: BTreeSet<&str> = edges
+        .iter()
+        .map(|edge| edge.to.as_str())
+        .filter(|to| !known.contains(to))
+        .collect();
+
+    let mut lines = vec![];
+    match format {
+        Format::Dot => {
+            lines.push("digraph world {".to_string());
+            lines.push("    node [shape=box];".into());
+            for location in &known {
+                lines.push(format!("    {};", dot_id(location)));
+            }
+            for location in &dangling {
+                lines.push(format!(
+                    "    {} [color=red, fontcolor=red, style=dashed];",
+                    dot_id(location)
+                ));
+            }
+            for edge in &edges {
+                let style = if dangling.contains(edge.to.as_str()) {
+                    ", color=red"
+                } else {
+                    ""
+                };
+                lines.push(format!(
+                    "    {} -> {} [label={}{}];",
+                    dot_id(&edge.from),
+                    dot_id(&edge.to),
+                    dot_id(&edge.label),
+                    style
+                ));
+            }
+            lines.push("}".into());
+        }
+        Format::Mermaid => {
+            lines.push("flowchart LR".to_string());
+            for location in &known {
+                lines.push(format!("    {}[\"{}\"]", mermaid_id(location), location));
+            }
+            for location in &dangling {
+                lines.push(format!(
+                    "    {}[\"{} (missing)\"]:::dangling",
+                    mermaid_id(location),
+                    location
+                ));
+            }
+            for edge in &edges {
+                lines.push(format!(
+                    "    {} -->|\"{}\"| {}",
+                    mermaid_id(&edge.from),
+                    edge.label.replace('"', "#quot;"),
+                    mermaid_id(&edge.to)
+                ));
+            }
+            if !dangling.is_empty() {
+                lines
+                    .push("    classDef dangling stroke:#f00,color:#f00,stroke-dasharray:5".into());
+            }
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+fn with_condition(label: String, condition: &Condition) -> String {
+    match condition {
+        Condition::Always => label,
+        _ => format!("{} [{}]", label, condition),
+    }
+}
+
+fn goto_targets(directives: &[Directive]) -> Vec<String> {
+    directives
+        .iter()
+        .flat_map(|directive| match directive {
+            Directive::GoTo(location) => vec![location.0.clone()],
+            Directive::Random(branches) => branches
+                .iter()
+                .flat_map(|(_, directives)| goto_targets(directives))
+                .collect(),
+            _ => vec![],
+        })
+        .collect()
+}
+
+fn dot_id(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// mermaid ids can't have spaces or most punctuation; location ids are already plain words
+fn mermaid_id(s: &str) -> String {
+    s.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::load_locations;
+
+    #[test]
+    fn test_edges() {
+        let locations = load_locations("assets");
+        let mut edges = edges(&locations);
+        edges.sort_by(|a, b| a.from.cmp(&b.from).then(a.to.cmp(&b.to)));
+        assert_eq!(
+            edges,
+            vec![
+                Edge {
+                    from: "bedroom".into(),
+                    to: "hallway".into(),
+                    label: "east".into(),
+                },
+                Edge {
+                    from: "hallway".into(),
+                    to: "bedroom".into(),
+                    label: "west".into(),
+                },
+                Edge {
+                    from: "hallway".into(),
+                    to: "hell".into(),
+                    label: "recall information from that letter [didReadLetter]".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_export_highlights_dangling() {
+        let locations = load_locations("assets");
+
+        let dot = export(&locations, Format::Dot);
+        assert!(dot.contains("\"hell\" [color=red, fontcolor=red, style=dashed];"));
+        assert!(dot.contains("\"bedroom\" -> \"hallway\" [label=\"east\"];"));
+
+        let mermaid = export(&locations, Format::Mermaid);
+        assert!(mermaid.contains("hell[\"hell (missing)\"]:::dangling"));
+        assert!(mermaid.contains("hallway -->|\"west\"| bedroom"));
+    }
+}
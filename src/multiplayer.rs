@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::engine::{Engine, Output};
+use crate::game::Game;
+use crate::parser::{LocationId, PLAYER_SCOPE};
+
+// longest name a player can pick
+const MAX_NAME: usize = 24;
+
+// does this property belong to one player when several share a world? their own
+// ($me.) properties do, and so does which locations they've visited. everything else
+// is the world's, and every player sees the same value
+pub fn is_player_property(property_id: &str) -> bool {
+    property_id.starts_with(PLAYER_SCOPE) || property_id.starts_with("visited_")
+}
+
+// one world played by several players at once. each player has their own engine, so
+// their own location, $me. properties (and with them their inventory) and events;
+// the world's properties and once-only actions are shared between all of them
+#[derive(Default)]
+pub struct SharedGame {
+    properties: HashMap<String, bool>,
+    used_actions: HashSet<String>,
+    players: BTreeMap<String, Player>,
+}
+
+struct Player {
+    engine: Engine,
+    // what's happened around this player since they were last told
+    inbox: Vec<Output>,
+}
+
+impl SharedGame {
+    pub fn new() -> SharedGame {
+        SharedGame::default()
+    }
+
+    // add a player to the world, returning what they see first. errors are worded
+    // for the player
+    pub fn join(&mut self, name: &str, mut engine: Engine) -> Result<Vec<Output>, String> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME || name.contains(char::is_control) {
+            return Err(format!(
+                "Names should be between 1 and {} characters long.",
+                MAX_NAME
+            ));
+        }
+        if self.players.contains_key(name) {
+            return Err(format!("Someone called {} is already here.", name));
+        }
+
+        self.share_with(&mut engine.game);
+        let mut outputs = engine.start();
+        self.take_from(&engine.game);
+
+        let location = engine.game.location.clone();
+        outputs.extend(self.who_else_is_at(&location, name));
+        self.tell_others_at(&location, name, Output::Text(format!("{} enters.", name)));
+        self.players.insert(
+            name.to_string(),
+            Player {
+                engine,
+                inbox: vec![],
+            },
+        );
+        Ok(outputs)
+    }
+
+    pub fn leave(&mut self, name: &str) {
+        if let Some(player) = self.players.remove(name) {
+            let location = player.engine.game.location;
+            self.tell_others_at(
+                &location,
+                name,
+                Output::Text(format!("{} fades from view.", name)),
+            );
+        }
+    }
+
+    // one line of input from a player: either something said to the players nearby,
+    // or commands for their engine
+    pub fn handle(&mut self, name: &str, input: &str) -> Vec<Output> {
+        let Some(location) = self.game(name).map(|game| game.location.clone()) else {
+            return vec![];
+        };
+
+        // the world's own actions come first, so "say password" can still open doors
+        let for_world = self
+            .players
+            .get(name)
+            .is_some_and(|player| player.engine.has_action_for(input));
+        if let Some(speech_text) = said(input).filter(|_| !for_world) {
+            if speech_text.is_empty() {
+                return vec![Output::Text("Say what?".into())];
+            }
+            // styled as the player's speech, like an npc's
+            let speech = |text| Output::Speech {
+                speaker: name.to_string(),
+                text,
+            };
+            self.tell_others_at(
+                &location,
+                name,
+                speech(format!("{} says, \"{}\"", name, speech_text)),
+            );
+            return vec![speech(format!("You say, \"{}\"", speech_text))];
+        }
+
+        let mut player = self.players.remove(name).expect("player should exist");
+        self.share_with(&mut player.engine.game);
+        let mut outputs = player.engine.handle(input);
+        self.take_from(&player.engine.game);
+        let arrived = player.engine.game.location.clone();
+        self.players.insert(name.to_string(), player);
+
+        if arrived != location {
+            self.tell_others_at(&location, name, Output::Text(format!("{} leaves.", name)));
+            self.tell_others_at(&arrived, name, Output::Text(format!("{} enters.", name)));
+            outputs.extend(self.who_else_is_at(&arrived, name));
+        }
+        outputs
+    }
+
+    // what happened around this player since the last time this was called
+    pub fn take_inbox(&mut self, name: &str) -> Vec<Output> {
+        self.players
+            .get_mut(name)
+            .map(|player| std::mem::take(&mut player.inbox))
+            .unwrap_or_default()
+    }
+
+    pub fn game(&self, name: &str) -> Option<&Game> {
+        self.players.get(name).map(|player| &player.engine.game)
+    }
+
+    // every player's name and game, by name
+    pub fn players(&self) -> impl Iterator<Item = (&str, &Game)> {
+        self.players
+            .iter()
+            .map(|(name, player)| (name.as_str(), &player.engine.game))
+    }
+
+    // bring a player's game up to date with what the others have done
+    fn share_with(&self, game: &mut Game) {
+        game.properties
+            .retain(|property_id, _| is_player_property(property_id));
+        game.properties.extend(
+            self.properties
+                .iter()
+                .map(|(property_id, value)| (property_id.clone(), *value)),
+        );
+        game.used_actions = self.used_actions.clone();
+    }
+
+    // and keep what a player's turn did to the world
+    fn take_from(&mut self, game: &Game) {
+        self.properties = game
+            .properties
+            .iter()
+            .filter(|(property_id, _)| !is_player_property(property_id))
+            .map(|(property_id, value)| (property_id.clone(), *value))
+            .collect();
+        self.used_actions = game.used_actions.clone();
+    }
+
+    fn tell_others_at(&mut self, location: &LocationId, name: &str, output: Output) {
+        for (other, player) in self.players.iter_mut() {
+            if other != name && player.engine.game.location == *location {
+                player.inbox.push(output.clone());
+            }
+        }
+    }
+
+    fn who_else_is_at(&self, location: &LocationId, name: &str) -> Option<Output> {
+        let others: Vec<&str> = self
+            .players()
+            .filter(|(other, game)| *other != name && game.location == *location)
+            .map(|(other, _)| other)
+            .collect();
+        match others.as_slice() {
+            [] => None,
+            [other] => Some(Output::Text(format!("{} is here.", other))),
+            [init @ .., last] => Some(Output::Text(format!(
+                "{} and {} are here.",
+                init.join(", "),
+                last
+            ))),
+        }
+    }
+}
+
+// `say hello` or `"hello` -> hello
+fn said(input: &str) -> Option<&str> {
+    let input = input.trim();
+    if let Some(speech) = input.strip_prefix('"') {
+        return Some(speech.trim_end_matches('"').trim());
+    }
+    let (verb, rest) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    verb.eq_ignore_ascii_case("say").then(|| rest.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::DescriptionCache;
+    use crate::engine::World;
+    use crate::narrator::OfflineNarrator;
+    use crate::parser::{Action, Condition, Directive, PropertyId};
+    use std::sync::Arc;
+
+    fn texts(outputs: Vec<Output>) -> Vec<String> {
+        outputs
+            .into_iter()
+            .map(|output| output.text().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_shared_game() {
+        let mut world = World::load("assets");
+        let bedroom = world
+            .locations
+            .iter_mut()
+            .find(|location| location.title == "bedroom")
+            .unwrap();
+        bedroom.actions.push(Action {
+            title: "take key".into(),
+            aliases: vec![],
+            condition: Condition::Always,
+            directives: vec![Directive::SetProperty(
+                PropertyId("$me.hasKey".into()),
+                true,
+            )],
+            else_directives: vec![],
+            fail: None,
+            hidden: false,
+            once: false,
+        });
+        bedroom.actions.push(Action {
+            title: "say xyzzy".into(),
+            aliases: vec![],
+            condition: Condition::Always,
+            directives: vec![Directive::Say("Nothing happens.".into())],
+            else_directives: vec![],
+            fail: None,
+            hidden: false,
+            once: false,
+        });
+        let world = Arc::new(world);
+        let engine = || {
+            Engine::new(
+                Arc::clone(&world),
+                Box::new(OfflineNarrator),
+                DescriptionCache::default(),
+            )
+        };
+
+        let mut game = SharedGame::new();
+        game.join("Alice", engine()).unwrap();
+        let intro = texts(game.join("Bob", engine()).unwrap());
+        assert!(intro.contains(&"Alice is here.".to_string()));
+        assert_eq!(texts(game.take_inbox("Alice")), vec!["Bob enters."]);
+        assert!(game.join("Bob", engine()).is_err());
+
+        // only players in the same place hear each other
+        assert_eq!(texts(game.handle("Bob", "say hi")), vec!["You say, \"hi\""]);
+        assert_eq!(texts(game.take_inbox("Alice")), vec!["Bob says, \"hi\""]);
+        // unless the world has a use for what was said
+        assert!(texts(game.handle("Bob", "say xyzzy")).contains(&"Nothing happens.".to_string()));
+        assert!(game.take_inbox("Alice").is_empty());
+
+        // the key is Alice's, but reading the letter is something the world remembers
+        game.handle("Alice", "take key");
+        game.handle("Alice", "read letter");
+        assert_eq!(game.properties.get("didReadLetter"), Some(&true));
+        assert!(texts(game.handle("Bob", "inventory"))
+            .contains(&"You aren't carrying anything.".to_string()));
+        assert!(texts(game.handle("Alice", "inventory"))
+            .contains(&"You are carrying: key.".to_string()));
+        assert_eq!(
+            game.game("Bob").unwrap().properties.get("didReadLetter"),
+            Some(&true)
+        );
+
+        game.handle("Alice", "go east");
+        assert_eq!(texts(game.take_inbox("Bob")), vec!["Alice leaves."]);
+        game.handle("Bob", "say bye");
+        assert!(game.take_inbox("Alice").is_empty());
+    }
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use yaml_rust2::{yaml::Hash, Yaml, YamlEmitter, YamlLoader};
+
+// one request to the narrator; mirrors the three prompts in ai.py
+#[derive(Debug, Clone)]
+pub enum Prompt {
+    // which of the available actions (if any) does the player's input match?
+    Action {
+        actions: Vec<String>,
+        question: String,
+    },
+    // embellish a short location description
+    Description { description: String },
+    // describe the scene after the player does something
+    Scene {
+        location: String,
+        description: String,
+        items: String,
+        question: String,
+    },
+}
+
+impl Prompt {
+    // canonical single-line form of the prompt. this is both what gets sent to
+    // ai.py and what gets hashed when recording/replaying
+    pub fn to_line(&self) -> String {
+        let fields: Vec<String> = match self {
+            Prompt::Action { actions, question } => {
+                vec!["action".into(), actions.join("\n"), question.clone()]
+            }
+            Prompt::Description { description } => {
+                vec!["description".into(), description.clone()]
+            }
+            Prompt::Scene {
+                location,
+                description,
+                items,
+                question,
+            } => vec![
+                "scene".into(),
+                location.clone(),
+                description.clone(),
+                items.clone(),
+                question.clone(),
+            ],
+        };
+        fields
+            .iter()
+            .map(|field| escape(field))
+            .collect::<Vec<String>>()
+            .join("\t")
+    }
+
+    // stable hash of the prompt, used as the fixture key
+    pub fn hash(&self) -> String {
+        format!("{:016x}", fnv1a(self.to_line().as_bytes()))
+    }
+}
+
+// anything that can turn a prompt into a response. Send, so games can be handed
+// between server threads
+pub trait Narrator: Send {
+    fn narrate(&mut self, prompt: &Prompt) -> io::Result<String>;
+}
+
+// pick a narrator backend based on WWIZARD_NARRATOR:
+//      unset / "offline"   no AI; descriptions are used verbatim
+//      "python"            live responses from ai.py (needs GOOGLE_API_KEY)
+//      "record:<path>"     live responses, saved to a fixture file as they arrive
+//      "replay:<path>"     responses played back from a fixture file
+pub fn from_env() -> io::Result<Box<dyn Narrator>> {
+    let setting = env::var("WWIZARD_NARRATOR").unwrap_or_default();
+    match setting.split_once(':') {
+        None if setting.is_empty() || setting == "offline" => Ok(Box::new(OfflineNarrator)),
+        None if setting == "python" => Ok(Box::new(PythonNarrator::spawn()?)),
+        Some(("record", path)) => Ok(Box::new(RecordingNarrator::new(
+            PythonNarrator::spawn()?,
+            path,
+        )?)),
+        Some(("replay", path)) => Ok(Box::new(ReplayNarrator::load(path)?)),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Unknown WWIZARD_NARRATOR setting: {}", setting),
+        )),
+    }
+}
+
+// ===================================================================================
+// OFFLINE: no AI at all, just enough to play the game
+
+pub struct OfflineNarrator;
+
+impl Narrator for OfflineNarrator {
+    fn narrate(&mut self, prompt: &Prompt) -> io::Result<String> {
+        let response = match prompt {
+            Prompt::Action { actions, question } => actions
+                .iter()
+                .find(|action| action.eq_ignore_ascii_case(question.trim()))
+                .cloned()
+                .unwrap_or_else(|| "None".into()),
+            Prompt::Description { description } => description.clone(),
+            Prompt::Scene { description, .. } => description.clone(),
+        };
+        Ok(response)
+    }
+}
+
+// ===================================================================================
+// PYTHON: talks to `python3 src/ai.py serve`, one escaped line per prompt/response
+
+pub struct PythonNarrator {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl PythonNarrator {
+    pub fn spawn() -> io::Result<PythonNarrator> {
+        let mut child = Command::new("python3")
+            .args(["src/ai.py", "serve"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("Child stdin should be piped.");
+        let stdout = child.stdout.take().expect("Child stdout should be piped.");
+        Ok(PythonNarrator {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+}
+
+impl Narrator for PythonNarrator {
+    fn narrate(&mut self, prompt: &Prompt) -> io::Result<String> {
+        writeln!(self.stdin, "{}", prompt.to_line())?;
+        self.stdin.flush()?;
+
+        let mut line = String::new();
+        if self.stdout.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "ai.py exited without responding",
+            ));
+        }
+        Ok(unescape(line.trim_end_matches('\n')))
+    }
+}
+
+impl Drop for PythonNarrator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ===================================================================================
+// RECORD / REPLAY: deterministic, offline sessions for tests
+
+// a single recorded prompt/response pair
+#[derive(Debug)]
+struct Recording {
+    hash: String,
+    prompt: String,
+    response: String,
+}
+
+// wraps another narrator and writes every prompt/response pair to a fixture file.
+// the file is rewritten after every response so a crash doesn't lose the session
+pub struct RecordingNarrator<N: Narrator> {
+    inner: N,
+    path: PathBuf,
+    recordings: Vec<Recording>,
+}
+
+impl<N: Narrator> RecordingNarrator<N> {
+    // appends to the fixture if it already exists
+    pub fn new(inner: N, path: impl AsRef<Path>) -> io::Result<RecordingNarrator<N>> {
+        let path = path.as_ref().to_path_buf();
+        let recordings = if path.exists() {
+            read_fixture(&path)?
+        } else {
+            vec![]
+        };
+        Ok(RecordingNarrator {
+            inner,
+            path,
+            recordings,
+        })
+    }
+}
+
+impl<N: Narrator> Narrator for RecordingNarrator<N> {
+    fn narrate(&mut self, prompt: &Prompt) -> io::Result<String> {
+        let response = self.inner.narrate(prompt)?;
+        let hash = prompt.hash();
+
+        // re-recording a prompt replaces the old response
+        self.recordings.retain(|recording| recording.hash != hash);
+        self.recordings.push(Recording {
+            hash,
+            prompt: prompt.to_line(),
+            response: response.clone(),
+        });
+        write_fixture(&self.path, &self.recordings)?;
+
+        Ok(response)
+    }
+}
+
+// answers prompts from a fixture file; a prompt that wasn't recorded is an error
+pub struct ReplayNarrator {
+    responses: HashMap<String, String>,
+}
+
+impl ReplayNarrator {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<ReplayNarrator> {
+        let responses = read_fixture(path.as_ref())?
+            .into_iter()
+            .map(|recording| (recording.hash, recording.response))
+            .collect();
+        Ok(ReplayNarrator { responses })
+    }
+}
+
+impl Narrator for ReplayNarrator {
+    fn narrate(&mut self, prompt: &Prompt) -> io::Result<String> {
+        self.responses.get(&prompt.hash()).cloned().ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "No recorded response for prompt {}: {}",
+                    prompt.hash(),
+                    prompt.to_line()
+                ),
+            )
+        })
+    }
+}
+
+fn read_fixture(path: &Path) -> io::Result<Vec<Recording>> {
+    let source = fs::read_to_string(path)?;
+    let docs = YamlLoader::load_from_str(&source)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+    let Some(doc) = docs.first() else {
+        return Ok(vec![]);
+    };
+
+    let invalid = |message: String| io::Error::new(ErrorKind::InvalidData, message);
+    let field = |entry: &Hash, key: &str| -> io::Result<String> {
+        match entry.get(&Yaml::String(key.into())) {
+            Some(Yaml::String(value)) => Ok(value.clone()),
+            Some(_) => Err(invalid(format!(
+                "fixture entry field {} should be a string",
+                key
+            ))),
+            None => Err(invalid(format!("fixture entry should have a {}", key))),
+        }
+    };
+
+    let Yaml::Array(entries) = doc else {
+        return Err(invalid("fixture should be a YAML array".into()));
+    };
+    entries
+        .iter()
+        .map(|yaml_entry| {
+            let Yaml::Hash(entry) = yaml_entry else {
+                return Err(invalid("fixture entry should be a YAML mapping".into()));
+            };
+            Ok(Recording {
+                hash: field(entry, "hash")?,
+                prompt: field(entry, "prompt")?,
+                response: field(entry, "response")?,
+            })
+        })
+        .collect()
+}
+
+fn write_fixture(path: &Path, recordings: &[Recording]) -> io::Result<()> {
+    let entries = recordings
+        .iter()
+        .map(|recording| {
+            let mut entry = Hash::new();
+            for (key, value) in [
+                ("hash", &recording.hash),
+                ("prompt", &recording.prompt),
+                ("response", &recording.response),
+            ] {
+                entry.insert(Yaml::String(key.into()), Yaml::String(value.clone()));
+            }
+            Yaml::Hash(entry)
+        })
+        .collect();
+
+    let mut out = String::new();
+    YamlEmitter::new(&mut out)
+        .dump(&Yaml::Array(entries))
+        .map_err(|e| io::Error::other(format!("{:?}", e)))?;
+    out.push('\n');
+    fs::write(path, out)
+}
+
+// ===================================================================================
+// HELPERS
+
+// 64-bit FNV-1a. std's hashers aren't guaranteed stable between releases, and
+// fixture files need to outlive compiler upgrades
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn unescape(field: &str) -> String {
+    let mut s = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            s.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => s.push('\n'),
+            Some('t') => s.push('\t'),
+            Some(other) => s.push(other),
+            None => s.push('\\'),
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_round_trip() {
+        let s = "a line\nanother\tline with a \\ backslash";
+        assert!(!escape(s).contains('\n'));
+        assert_eq!(unescape(&escape(s)), s);
+    }
+
+    #[test]
+    fn test_record_then_replay() {
+        let path = env::temp_dir().join(format!("wwizard-record-{}.yaml", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let prompt = Prompt::Description {
+            description: "A small room.\nIt is tidy.".into(),
+        };
+        let mut recorder = RecordingNarrator::new(OfflineNarrator, &path).unwrap();
+        let recorded = recorder.narrate(&prompt).unwrap();
+
+        let mut replayer = ReplayNarrator::load(&path).unwrap();
+        assert_eq!(replayer.narrate(&prompt).unwrap(), recorded);
+
+        let unrecorded = Prompt::Description {
+            description: "A different room.".into(),
+        };
+        assert_eq!(
+            replayer.narrate(&unrecorded).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_malformed_fixture() {
+        let path = env::temp_dir().join(format!("wwizard-malformed-{}.yaml", std::process::id()));
+        for source in [
+            "hash: abc\n",
+            "- hash: abc\n  prompt: p\n",
+            "- hash: [abc]\n  prompt: p\n  response: r\n",
+        ] {
+            fs::write(&path, source).unwrap();
+            assert_eq!(
+                read_fixture(&path).unwrap_err().kind(),
+                ErrorKind::InvalidData
+            );
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
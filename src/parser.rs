@@ -1,46 +1,269 @@
 use nom::branch::alt;
-use nom::bytes::complete::tag;
-use nom::character::complete::{alphanumeric1, char};
-use nom::combinator::all_consuming;
-use nom::sequence::{preceded, separated_pair};
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::char;
+use nom::combinator::{all_consuming, opt, recognize};
+use nom::sequence::{pair, preceded, separated_pair};
 use nom::IResult;
+use std::collections::HashMap;
+use std::fmt;
 use std::fs;
+use std::path::Path;
 use yaml_rust2::{yaml::Hash, Yaml, YamlLoader};
 
 #[derive(Debug)]
 pub struct Location {
     pub title: String,
     pub actions: Vec<Action>,
+    pub exits: Vec<Exit>,
+    pub objects: Vec<Object>,
     pub description: String,
+    pub on_enter: Option<Hook>,
+    pub on_exit: Option<Hook>,
+    // runs once, the first time the player arrives
+    pub first_visit: Option<Hook>,
 }
 
+// text and directives that run when something happens to a location
+#[derive(Debug, Clone)]
+pub struct Hook {
+    pub text: Option<String>,
+    pub directives: Vec<Directive>,
+}
+
+// scenery the player can examine
 #[derive(Debug)]
+pub struct Object {
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Debug)]
+pub struct Exit {
+    pub direction: Direction,
+    pub target: LocationId,
+    // the exit is locked while this is false
+    pub condition: Condition,
+    pub locked_message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+    Up,
+    Down,
+    In,
+    Out,
+}
+
+// every word Direction::from_word understands: the full names and the usual abbreviations
+pub const DIRECTION_WORDS: [(&str, Direction); 22] = [
+    ("north", Direction::North),
+    ("n", Direction::North),
+    ("northeast", Direction::NorthEast),
+    ("ne", Direction::NorthEast),
+    ("east", Direction::East),
+    ("e", Direction::East),
+    ("southeast", Direction::SouthEast),
+    ("se", Direction::SouthEast),
+    ("south", Direction::South),
+    ("s", Direction::South),
+    ("southwest", Direction::SouthWest),
+    ("sw", Direction::SouthWest),
+    ("west", Direction::West),
+    ("w", Direction::West),
+    ("northwest", Direction::NorthWest),
+    ("nw", Direction::NorthWest),
+    ("up", Direction::Up),
+    ("u", Direction::Up),
+    ("down", Direction::Down),
+    ("d", Direction::Down),
+    ("in", Direction::In),
+    ("out", Direction::Out),
+];
+
+impl Direction {
+    // accepts both the full name and the usual abbreviation, in any case, e.g. "north" or "N"
+    pub fn from_word(word: &str) -> Option<Direction> {
+        let word = word.to_ascii_lowercase();
+        DIRECTION_WORDS
+            .iter()
+            .find(|(direction_word, _)| *direction_word == word)
+            .map(|(_, direction)| *direction)
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Direction::North => "north",
+            Direction::NorthEast => "northeast",
+            Direction::East => "east",
+            Direction::SouthEast => "southeast",
+            Direction::South => "south",
+            Direction::SouthWest => "southwest",
+            Direction::West => "west",
+            Direction::NorthWest => "northwest",
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::In => "in",
+            Direction::Out => "out",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Action {
     pub title: String,
+    // other ways of saying the title, for the command parser
+    pub aliases: Vec<String>,
     pub condition: Condition,
     pub directives: Vec<Directive>,
+    // applied instead of directives when the condition is false
+    pub else_directives: Vec<Directive>,
+    // told to the player when the condition is false
+    pub fail: Option<String>,
+    // left out of help and the narrator's list; the player has to know to ask
+    pub hidden: bool,
+    // can only be done successfully once
+    pub once: bool,
+}
+
+impl Action {
+    // whether trying this action while its condition is false does anything at all
+    pub fn has_feedback(&self) -> bool {
+        self.fail.is_some() || !self.else_directives.is_empty()
+    }
+}
+
+// a kind of message with its own look: the narrator, an npc, or anything else a world
+// wants to say things as
+#[derive(Debug)]
+pub struct Voice {
+    // the category messages in this voice have; npcs' voices are their names
+    pub name: String,
+    // how frontends draw it, e.g. "bold lightmagenta". left to each frontend to read
+    pub style: Option<String>,
+    // exact inputs (lowercase) -> a reply in this voice. asides don't take a turn
+    pub asides: Vec<(String, String)>,
 }
 
 #[derive(Debug)]
+pub struct Npc {
+    pub name: String,
+    pub location: LocationId,
+    pub description: String,
+    // dialogue node id -> node. conversations begin at "start"
+    pub dialogue: HashMap<String, DialogueNode>,
+}
+
+#[derive(Debug)]
+pub struct DialogueNode {
+    pub text: String,
+    pub options: Vec<DialogueOption>,
+}
+
+#[derive(Debug)]
+pub struct DialogueOption {
+    pub text: String,
+    // the option is only offered while this is true
+    pub condition: Condition,
+    pub directives: Vec<Directive>,
+    // the node to continue to; None ends the conversation
+    pub next: Option<String>,
+}
+
+// something that happens on its own rather than because of a player command
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub id: String,
+    pub trigger: Trigger,
+    // turns to wait after the trigger before firing
+    pub after: u32,
+    pub text: Option<String>,
+    pub directives: Vec<Directive>,
+    // fire every time the trigger happens rather than just the first
+    pub repeat: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    // the start of the game
+    Start,
+    // the player walking into a location
+    Enter(LocationId),
+    // a condition turning from false to true
+    When(Condition),
+}
+
+#[derive(Debug, Clone)]
 pub enum Directive {
     SetProperty(PropertyId, PropertyValue),
     GoTo(LocationId),
+    Toggle(PropertyId),
+    // post a message
+    Say(String),
+    // pick one branch, with probability proportional to its weight
+    Random(Vec<(u32, Vec<Directive>)>),
+    // end the game, with an optional closing message
+    Win(Option<String>),
+    Lose(Option<String>),
+    // re-show the current location
+    Describe,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Condition {
+    Always,
     And(Box<Condition>, Box<Condition>),
     Or(Box<Condition>, Box<Condition>),
     Not(Box<Condition>),
     IsPropertyTrue(PropertyId),
 }
 
+impl Condition {
+    // every property id this condition reads
+    pub fn properties(&self) -> Vec<&str> {
+        match self {
+            Condition::Always => vec![],
+            Condition::IsPropertyTrue(PropertyId(property_id)) => vec![property_id.as_str()],
+            Condition::Not(b) => b.properties(),
+            Condition::Or(b1, b2) | Condition::And(b1, b2) => {
+                let mut properties = b1.properties();
+                properties.extend(b2.properties());
+                properties
+            }
+        }
+    }
+}
+
+// the same syntax the world files use, e.g. `!didReadLetter`
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Always => write!(f, "always"),
+            Condition::IsPropertyTrue(PropertyId(property_id)) => write!(f, "{}", property_id),
+            Condition::Not(b) => write!(f, "!{}", b),
+            Condition::Or(b1, b2) => write!(f, "{} | {}", b1, b2),
+            Condition::And(b1, b2) => write!(f, "{} & {}", b1, b2),
+        }
+    }
+}
+
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct ItemId(pub u32);
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PropertyId(pub String);
 pub type PropertyValue = bool;
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct LocationId(pub String);
 
 pub trait YamlExt {
@@ -74,6 +297,22 @@ impl YamlExt for Yaml {
     }
 }
 
+// the key-value pair of a one-entry mapping like `- east: hallway`. a second pair is
+// usually a missing `-`, and would otherwise be silently dropped, so it's an error
+fn single_pair<'a>(mapping: &'a Hash, what: &str) -> (&'a Yaml, &'a Yaml) {
+    let mut pairs = mapping.iter();
+    let (yaml_key, yaml_value) = pairs
+        .next()
+        .unwrap_or_else(|| panic!("{} should have a key-value pair.", what));
+    if let Some((yaml_extra, _)) = pairs.next() {
+        panic!(
+            "{} {:?} should be the only key in its mapping, but found {:?} too. Start each one with its own -.",
+            what, yaml_key, yaml_extra
+        );
+    }
+    (yaml_key, yaml_value)
+}
+
 trait IResultExt<I, O, P, E> {
     fn map_val(self, f: impl Fn(O) -> P) -> IResult<I, P, E>;
 }
@@ -84,8 +323,39 @@ impl<I, O, P, E> IResultExt<I, O, P, E> for IResult<I, O, E> {
     }
 }
 
+fn parse_always(input: &str) -> IResult<&str, Condition> {
+    tag("always")(input).map_val(|_| Condition::Always)
+}
+
+// properties starting with this belong to each player rather than the world, when
+// several players share one (e.g. `$me.hasKey`). single-player games don't care
+pub const PLAYER_SCOPE: &str = "$me.";
+
+// the characters of a property id, as a regex class. the schema's patterns are built
+// from this, so it has to say the same as is_property_char
+pub const PROPERTY_CHARS: &str = "A-Za-z0-9_";
+
+fn is_property_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn parse_property_id(input: &str) -> IResult<&str, &str> {
+    recognize(pair(opt(tag(PLAYER_SCOPE)), take_while1(is_property_char)))(input)
+}
+
+// the whole of a word in a set or toggle directive
+fn parse_property_word(word: &str, keyword: &str) -> Result<PropertyId, String> {
+    match all_consuming(parse_property_id)(word) {
+        Ok((_, property_id)) => Ok(PropertyId(property_id.into())),
+        Err(_) => Err(format!(
+            "{} directive property id should be letters, digits and underscores, not: {}",
+            keyword, word
+        )),
+    }
+}
+
 fn parse_is_property_true(input: &str) -> IResult<&str, Condition> {
-    alphanumeric1(input)
+    parse_property_id(input)
         .map_val(|property_id| Condition::IsPropertyTrue(PropertyId(property_id.into())))
 }
 
@@ -93,61 +363,213 @@ fn parse_not(input: &str) -> IResult<&str, Condition> {
     preceded(char('!'), parse_condition)(input).map_val(|v| Condition::Not(Box::new(v)))
 }
 
+// TODO: these are left-recursive and never terminate; not wired into parse_condition yet
+#[allow(dead_code)]
 fn parse_or(input: &str) -> IResult<&str, Condition> {
     separated_pair(parse_condition, tag(" | "), parse_condition)(input)
         .map_val(|(a, b)| Condition::Or(Box::new(a), Box::new(b)))
 }
 
+#[allow(dead_code)]
 fn parse_and(input: &str) -> IResult<&str, Condition> {
     separated_pair(parse_condition, tag(" & "), parse_condition)(input)
         .map_val(|(a, b)| Condition::And(Box::new(a), Box::new(b)))
 }
 
-fn parse_condition(input: &str) -> IResult<&str, Condition> {
+pub fn parse_condition(input: &str) -> IResult<&str, Condition> {
     alt((
+        all_consuming(parse_always),
         all_consuming(parse_is_property_true),
         all_consuming(parse_not),
     ))(input)
 }
 
+// directives are strings, except for random, which is a mapping of weights to directives:
+//      - say "The coin spins."
+//      - random:
+//        - 3:
+//          - say "Heads."
+//        - 1:
+//          - say "Tails."
 fn parse_directive(yaml_directive: &Yaml) -> Directive {
-    let mut words = yaml_directive
-        .expect_string("Directive should be a string.")
-        .split_whitespace();
+    if let Yaml::Hash(mapping) = yaml_directive {
+        let (yaml_keyword, branches) = single_pair(mapping, "Directive mapping");
+        assert!(
+            yaml_keyword.as_str() == Some("random"),
+            "Directive mapping should be a random directive."
+        );
+        return parse_random(branches);
+    }
 
-    match words.next() {
-        Some("set") => {
+    let directive_string = yaml_directive.expect_string("Directive should be a string.");
+    parse_directive_str(directive_string).unwrap_or_else(|message| panic!("{}", message))
+}
+
+// one directive string, e.g. `set didReadLetter true`. errors are messages for the world author
+pub fn parse_directive_str(directive_string: &str) -> Result<Directive, String> {
+    let (keyword, rest) = directive_string
+        .trim()
+        .split_once(char::is_whitespace)
+        .unwrap_or((directive_string.trim(), ""));
+    let rest = rest.trim();
+    let mut words = rest.split_whitespace();
+
+    let directive = match keyword {
+        "set" => {
             let property_id = words
                 .next()
-                .expect("Set directive should have a property id.");
+                .ok_or("Set directive should have a property id.")?;
             let property_value = words
                 .next()
-                .expect("Set directive should have a property value.");
+                .ok_or("Set directive should have a property value.")?;
             let property_boolean = match property_value {
                 "true" => true,
                 "false" => false,
-                _ => panic!("Set directive property value should either be true or false"),
+                _ => return Err("Set directive property value should either be true or false".into()),
             };
-            Directive::SetProperty(PropertyId(property_id.into()), property_boolean)
+            Directive::SetProperty(parse_property_word(property_id, "Set")?, property_boolean)
         }
-        Some("goto") => {
+        "goto" => {
             let location_id = words
                 .next()
-                .expect("GoTo directive should have a location id.");
+                .ok_or("GoTo directive should have a location id.")?;
             Directive::GoTo(LocationId(location_id.into()))
         }
-        _ => panic!("Directive first word should either be set or goto."),
+        "toggle" => {
+            let property_id = words
+                .next()
+                .ok_or("Toggle directive should have a property id.")?;
+            Directive::Toggle(parse_property_word(property_id, "Toggle")?)
+        }
+        "say" => Directive::Say(parse_quoted(rest).ok_or_else(|| {
+            format!(
+                "Say directive should have its text in double quotes, e.g. say \"Hello.\", not: {}",
+                directive_string
+            )
+        })?),
+        "win" | "lose" => {
+            let message = match rest {
+                "" => None,
+                _ => Some(parse_quoted(rest).ok_or_else(|| {
+                    format!(
+                        "{} directive message should be in double quotes, e.g. {} \"The end.\", not: {}",
+                        keyword, keyword, directive_string
+                    )
+                })?),
+            };
+            if keyword == "win" {
+                Directive::Win(message)
+            } else {
+                Directive::Lose(message)
+            }
+        }
+        "describe" => Directive::Describe,
+        _ => {
+            return Err(format!(
+                "Directive first word should be one of set, goto, toggle, say, win, lose or describe, not: {}",
+                directive_string
+            ))
+        }
+    };
+
+    // everything except say/win/lose has a fixed number of words
+    if !matches!(
+        directive,
+        Directive::Say(_) | Directive::Win(_) | Directive::Lose(_)
+    ) && words.next().is_some()
+    {
+        return Err(format!(
+            "Directive has too many words: {}",
+            directive_string
+        ));
     }
+    Ok(directive)
+}
+
+fn parse_random(yaml_branches: &Yaml) -> Directive {
+    let branches: Vec<(u32, Vec<Directive>)> = yaml_branches
+        .expect_array("Random directive should be an array of weight-directives pairs.")
+        .iter()
+        .map(|yaml_branch| {
+            let (yaml_weight, yaml_directives) = single_pair(
+                yaml_branch.expect_hash("Random branch should be a YAML mapping."),
+                "Random branch",
+            );
+            let weight = match yaml_weight {
+                Yaml::Integer(weight) => u32::try_from(*weight).ok().filter(|weight| *weight > 0),
+                _ => None,
+            }
+            .unwrap_or_else(|| {
+                panic!(
+                    "Random branch weight {:?} should be a whole number from 1 to {}.",
+                    yaml_weight,
+                    u32::MAX
+                )
+            });
+            let directives = yaml_directives
+                .expect_array("Random branch directives should be an array.")
+                .iter()
+                .map(parse_directive)
+                .collect();
+            (weight, directives)
+        })
+        .collect();
+    assert!(
+        !branches.is_empty(),
+        "Random directive should have at least one branch."
+    );
+    Directive::Random(branches)
 }
 
+// the text between a pair of double quotes that make up the whole input
+fn parse_quoted(input: &str) -> Option<String> {
+    let text = input.strip_prefix('"')?.strip_suffix('"')?;
+    Some(text.into())
+}
+
+// actions come in a compact form and an explicit form, which parse to the same thing:
+//      - unlock door:
+//          hasKey:
+//          - set isDoorOpen true
+//          fail: The door is locked.
+//          else:
+//          - set triedDoor true
+//
+//      - title: unlock door
+//        aliases: [open door, use key on door]
+//        when: hasKey
+//        do:
+//        - set isDoorOpen true
+//        fail: The door is locked.
+//        else:
+//        - set triedDoor true
+//        hidden: false
+//        once: true
 fn parse_action(yaml_action: &Yaml) -> Action {
-    let mut title_details_mapping: Hash = yaml_action
-        .expect_hash("Action should be a YAML mapping.")
-        .clone();
+    let mapping = yaml_action.expect_hash("Action should be a YAML mapping.");
+    // a title key on its own, holding conditions, is a compact action for typing "title"
+    let title = mapping.get(&Yaml::String("title".into()));
+    let compact_title = mapping.len() == 1 && matches!(title, Some(Yaml::Hash(_)));
+    if title.is_some() && !compact_title {
+        parse_explicit_action(mapping)
+    } else {
+        parse_compact_action(mapping)
+    }
+}
+
+fn parse_compact_action(mapping: &Hash) -> Action {
+    let mut title_details_mapping: Hash = mapping.clone();
 
     let (yaml_title, yaml_details) = title_details_mapping
         .pop_front()
         .expect("Action should have a title-details pair.");
+    let title = yaml_title.expect_string("Action tile should be a string.");
+    if let Some((yaml_extra, _)) = title_details_mapping.front() {
+        panic!(
+            "Action {} should be the only key in its mapping, but found {:?} too. Use the explicit form (title, when, do) for more options.",
+            title, yaml_extra
+        );
+    }
 
     let mut condition_directive_mapping: Hash = yaml_details
         .expect_hash("Action details should be a YAML mapping.")
@@ -159,23 +581,204 @@ fn parse_action(yaml_action: &Yaml) -> Action {
 
     let condition_string = yaml_condition.expect_string("Condition should be a string.");
 
-    let directives: Vec<Directive> = yaml_directives
-        .expect_array("Action directives should be an array.")
-        .iter()
-        .map(parse_directive)
-        .collect();
+    // the condition can only be followed by else and fail. anything else is most likely
+    // a second condition, which the compact form can't express
+    for yaml_key in condition_directive_mapping.keys() {
+        if !matches!(yaml_key.as_str(), Some("else" | "fail")) {
+            panic!(
+                "Action {} should have a single condition, but found {:?} as well. Split it into separate actions or use the explicit form.",
+                title, yaml_key
+            );
+        }
+    }
 
     Action {
-        title: yaml_title
-            .expect_string("Action tile should be a string.")
-            .into(),
+        title: title.into(),
+        aliases: vec![],
         condition: parse_condition(condition_string.as_str())
             .expect("Condition should parse correctly.")
             .1,
-        directives,
+        directives: parse_directive_list(Some(&yaml_directives), "Action directives"),
+        else_directives: parse_directive_list(
+            condition_directive_mapping.get(&Yaml::String("else".into())),
+            "Action else directives",
+        ),
+        fail: parse_optional_string(
+            condition_directive_mapping.get(&Yaml::String("fail".into())),
+            "Action fail message",
+        ),
+        hidden: false,
+        once: false,
+    }
+}
+
+fn parse_explicit_action(mapping: &Hash) -> Action {
+    let get = |key: &str| mapping.get(&Yaml::String(key.into()));
+
+    let title = get("title")
+        .expect("Action should have a title.")
+        .expect_string("Action title should be a string.");
+
+    for yaml_key in mapping.keys() {
+        let known = [
+            "title", "aliases", "when", "do", "else", "fail", "hidden", "once",
+        ];
+        if !yaml_key.as_str().is_some_and(|key| known.contains(&key)) {
+            panic!(
+                "Action {} has unknown key {:?}; expected one of {}.",
+                title,
+                yaml_key,
+                known.join(", ")
+            );
+        }
+    }
+
+    let aliases = match get("aliases") {
+        Some(yaml_aliases) => yaml_aliases
+            .expect_array("Action aliases should be an array.")
+            .iter()
+            .map(|yaml_alias| {
+                yaml_alias
+                    .expect_string("Action alias should be a string.")
+                    .clone()
+            })
+            .collect(),
+        None => vec![],
+    };
+    let condition = match get("when") {
+        Some(yaml_condition) => {
+            parse_condition(
+                yaml_condition
+                    .expect_string("Action condition should be a string.")
+                    .as_str(),
+            )
+            .expect("Action condition should parse correctly.")
+            .1
+        }
+        None => Condition::Always,
+    };
+    let flag = |key: &str| match get(key) {
+        Some(Yaml::Boolean(value)) => *value,
+        Some(_) => panic!("Action {} {} should be true or false.", title, key),
+        None => false,
+    };
+
+    Action {
+        title: title.clone(),
+        aliases,
+        condition,
+        directives: parse_directive_list(get("do"), "Action directives"),
+        else_directives: parse_directive_list(get("else"), "Action else directives"),
+        fail: parse_optional_string(get("fail"), "Action fail message"),
+        hidden: flag("hidden"),
+        once: flag("once"),
     }
 }
 
+fn parse_directive_list(yaml_directives: Option<&Yaml>, what: &str) -> Vec<Directive> {
+    match yaml_directives {
+        Some(yaml_directives) => yaml_directives
+            .expect_array(&format!("{} should be an array.", what))
+            .iter()
+            .map(parse_directive)
+            .collect(),
+        None => vec![],
+    }
+}
+
+fn parse_optional_string(yaml_string: Option<&Yaml>, what: &str) -> Option<String> {
+    yaml_string.map(|yaml_string| {
+        yaml_string
+            .expect_string(&format!("{} should be a string.", what))
+            .clone()
+    })
+}
+
+// exits come in a short form and a long form:
+//      - east: hallway
+//      - north:
+//          to: attic
+//          when: hasLadder
+//          locked: The trapdoor is too high to reach.
+fn parse_exit(yaml_exit: &Yaml) -> Exit {
+    let (yaml_direction, yaml_target) = single_pair(
+        yaml_exit.expect_hash("Exit should be a YAML mapping."),
+        "Exit",
+    );
+
+    let direction_string = yaml_direction.expect_string("Exit direction should be a string.");
+    let direction = Direction::from_word(direction_string).unwrap_or_else(|| {
+        panic!(
+            "Exit direction {} should be a compass direction, up, down, in or out.",
+            direction_string
+        )
+    });
+
+    if let Yaml::String(target) = yaml_target {
+        return Exit {
+            direction,
+            target: LocationId(target.clone()),
+            condition: Condition::Always,
+            locked_message: None,
+        };
+    }
+
+    let details = yaml_target.expect_hash("Exit target should be a location id or a YAML mapping.");
+    let target = details
+        .get(&Yaml::String("to".into()))
+        .expect("Exit should have a target location.")
+        .expect_string("Exit target should be a location id.");
+    let condition = match details.get(&Yaml::String("when".into())) {
+        Some(yaml_condition) => {
+            parse_condition(
+                yaml_condition
+                    .expect_string("Exit condition should be a string.")
+                    .as_str(),
+            )
+            .expect("Exit condition should parse correctly.")
+            .1
+        }
+        None => Condition::Always,
+    };
+    let locked_message = details.get(&Yaml::String("locked".into())).map(|message| {
+        message
+            .expect_string("Exit locked message should be a string.")
+            .clone()
+    });
+
+    Exit {
+        direction,
+        target: LocationId(target.clone()),
+        condition,
+        locked_message,
+    }
+}
+
+//      on_enter:
+//        text: The floorboards creak.
+//        do:
+//        - set isCold true
+// both keys are optional
+fn parse_hook(yaml_hook: &Yaml) -> Hook {
+    let mapping = yaml_hook.expect_hash("Location hook should be a YAML mapping.");
+
+    let text = mapping.get(&Yaml::String("text".into())).map(|yaml_text| {
+        yaml_text
+            .expect_string("Hook text should be a string.")
+            .clone()
+    });
+    let directives = match mapping.get(&Yaml::String("do".into())) {
+        Some(yaml_directives) => yaml_directives
+            .expect_array("Hook directives should be an array.")
+            .iter()
+            .map(parse_directive)
+            .collect(),
+        None => vec![],
+    };
+
+    Hook { text, directives }
+}
+
 fn parse_location(source: &str, title: &str) -> Location {
     let docs = YamlLoader::load_from_str(source).expect("Location file should be in YAML format.");
     let doc = &docs[0];
@@ -198,18 +801,367 @@ fn parse_location(source: &str, title: &str) -> Location {
         .map(parse_action)
         .collect::<Vec<Action>>();
 
+    let yaml_exits = mapping
+        .get(&Yaml::String("exits".into()))
+        .unwrap_or(&default);
+
+    let exits: Vec<Exit> = yaml_exits
+        .expect_array("Location exits should be an array.")
+        .iter()
+        .map(parse_exit)
+        .collect();
+
+    let default_objects = Yaml::Hash(Hash::new());
+    let yaml_objects = mapping
+        .get(&Yaml::String("objects".into()))
+        .unwrap_or(&default_objects);
+
+    let objects: Vec<Object> = yaml_objects
+        .expect_hash("Location objects should be a YAML mapping.")
+        .iter()
+        .map(|(yaml_name, yaml_description)| Object {
+            name: yaml_name
+                .expect_string("Object name should be a string.")
+                .into(),
+            description: yaml_description
+                .expect_string("Object description should be a string.")
+                .into(),
+        })
+        .collect();
+
+    let hook = |key: &str| mapping.get(&Yaml::String(key.into())).map(parse_hook);
+
     Location {
         title: title.into(),
         actions,
+        exits,
+        objects,
         description: description.into(),
+        on_enter: hook("on_enter"),
+        on_exit: hook("on_exit"),
+        first_visit: hook("first_visit"),
+    }
+}
+
+// dialogue options come in a short form and a long form:
+//      - Goodbye.
+//      - Tell me about the letter.:
+//          when: didReadLetter
+//          do:
+//          - set askedAboutLetter true
+//          goto: letter
+fn parse_dialogue_option(yaml_option: &Yaml) -> DialogueOption {
+    if let Yaml::String(text) = yaml_option {
+        return DialogueOption {
+            text: text.clone(),
+            condition: Condition::Always,
+            directives: vec![],
+            next: None,
+        };
+    }
+
+    let (yaml_text, yaml_details) = single_pair(
+        yaml_option.expect_hash("Dialogue option should be a string or a YAML mapping."),
+        "Dialogue option",
+    );
+
+    let details = yaml_details.expect_hash("Dialogue option details should be a YAML mapping.");
+    let condition = match details.get(&Yaml::String("when".into())) {
+        Some(yaml_condition) => {
+            parse_condition(
+                yaml_condition
+                    .expect_string("Dialogue option condition should be a string.")
+                    .as_str(),
+            )
+            .expect("Dialogue option condition should parse correctly.")
+            .1
+        }
+        None => Condition::Always,
+    };
+    let directives = match details.get(&Yaml::String("do".into())) {
+        Some(yaml_directives) => yaml_directives
+            .expect_array("Dialogue option directives should be an array.")
+            .iter()
+            .map(parse_directive)
+            .collect(),
+        None => vec![],
+    };
+    let next = details.get(&Yaml::String("goto".into())).map(|yaml_next| {
+        yaml_next
+            .expect_string("Dialogue option goto should be a node id.")
+            .clone()
+    });
+
+    DialogueOption {
+        text: yaml_text
+            .expect_string("Dialogue option text should be a string.")
+            .into(),
+        condition,
+        directives,
+        next,
+    }
+}
+
+fn parse_dialogue_node(yaml_node: &Yaml) -> DialogueNode {
+    let mapping = yaml_node.expect_hash("Dialogue node should be a YAML mapping.");
+
+    let text = mapping
+        .get(&Yaml::String("text".into()))
+        .expect("Dialogue node should have text.")
+        .expect_string("Dialogue node text should be a string.");
+
+    let default = Yaml::Array(vec![]);
+    let options = mapping
+        .get(&Yaml::String("options".into()))
+        .unwrap_or(&default)
+        .expect_array("Dialogue node options should be an array.")
+        .iter()
+        .map(parse_dialogue_option)
+        .collect();
+
+    DialogueNode {
+        text: text.into(),
+        options,
+    }
+}
+
+//      name: jesse
+//      style: red
+//      asides:
+//        exit: go away jesse
+fn parse_voice(source: &str) -> Voice {
+    let docs = YamlLoader::load_from_str(source).expect("Voice file should be in YAML format.");
+    let mapping = docs[0].expect_hash("Voice should be a YAML mapping.");
+    let get = |key: &str| mapping.get(&Yaml::String(key.into()));
+
+    let asides = match get("asides") {
+        Some(yaml_asides) => yaml_asides
+            .expect_hash("Voice asides should be a YAML mapping.")
+            .iter()
+            .map(|(input, text)| {
+                (
+                    input
+                        .expect_string("Aside inputs should be strings.")
+                        .trim()
+                        .to_lowercase(),
+                    text.expect_string("Asides should be strings.").clone(),
+                )
+            })
+            .collect(),
+        None => vec![],
+    };
+    Voice {
+        name: get("name")
+            .expect("Voice should have a name.")
+            .expect_string("Voice name should be a string.")
+            .clone(),
+        style: get("style")
+            .map(|style| style.expect_string("Voice style should be a string.").clone()),
+        asides,
+    }
+}
+
+fn parse_npc(source: &str) -> Npc {
+    let docs = YamlLoader::load_from_str(source).expect("NPC file should be in YAML format.");
+    let doc = &docs[0];
+
+    let mapping = doc.expect_hash("NPC should be a YAML mapping.");
+    let field = |key: &str| -> &String {
+        mapping
+            .get(&Yaml::String(key.into()))
+            .unwrap_or_else(|| panic!("NPC should have a {}.", key))
+            .expect_string(&format!("NPC {} should be a string.", key))
+    };
+
+    let dialogue: HashMap<String, DialogueNode> = mapping
+        .get(&Yaml::String("dialogue".into()))
+        .expect("NPC should have dialogue.")
+        .expect_hash("NPC dialogue should be a YAML mapping.")
+        .iter()
+        .map(|(yaml_id, yaml_node)| {
+            (
+                yaml_id
+                    .expect_string("Dialogue node id should be a string.")
+                    .clone(),
+                parse_dialogue_node(yaml_node),
+            )
+        })
+        .collect();
+    assert!(
+        dialogue.contains_key("start"),
+        "NPC dialogue should have a start node."
+    );
+    // a typo in a goto would otherwise only show up mid-conversation
+    for (id, node) in &dialogue {
+        for option in &node.options {
+            if let Some(next) = &option.next {
+                assert!(
+                    dialogue.contains_key(next),
+                    "Dialogue option {:?} in node {} should goto an existing node, but there is no {}.",
+                    option.text,
+                    id,
+                    next
+                );
+            }
+        }
+    }
+
+    Npc {
+        name: field("name").clone(),
+        location: LocationId(field("location").clone()),
+        description: field("description").clone(),
+        dialogue,
+    }
+}
+
+// an event file is a list of events:
+//      - candle burns out:
+//          when: candleLit
+//          after: 5
+//          text: The candle gutters and goes out.
+//          do:
+//          - set candleLit false
+// the trigger is one of `when: <condition>` or `enter: <location>`. with neither,
+// the event is timed from the start of the game and needs an `after`
+fn parse_event(yaml_event: &Yaml) -> Event {
+    let (yaml_id, yaml_details) = single_pair(
+        yaml_event.expect_hash("Event should be a YAML mapping."),
+        "Event",
+    );
+    let id = yaml_id
+        .expect_string("Event id should be a string.")
+        .clone();
+
+    let details = yaml_details.expect_hash("Event details should be a YAML mapping.");
+    let get = |key: &str| details.get(&Yaml::String(key.into()));
+
+    let trigger = match (get("when"), get("enter")) {
+        (Some(yaml_condition), None) => Trigger::When(
+            parse_condition(
+                yaml_condition
+                    .expect_string("Event condition should be a string.")
+                    .as_str(),
+            )
+            .expect("Event condition should parse correctly.")
+            .1,
+        ),
+        (None, Some(yaml_location)) => Trigger::Enter(LocationId(
+            yaml_location
+                .expect_string("Event enter should be a location id.")
+                .clone(),
+        )),
+        (None, None) => Trigger::Start,
+        (Some(_), Some(_)) => panic!("Event {} should have at most one of when and enter.", id),
+    };
+
+    let after = match get("after") {
+        Some(Yaml::Integer(turns)) => u32::try_from(*turns).ok(),
+        Some(_) => None,
+        None => Some(0),
+    }
+    .unwrap_or_else(|| panic!("Event {} after should be a number of turns.", id));
+    if matches!(trigger, Trigger::Start) && after == 0 {
+        panic!("Event {} should have a when, an enter or an after.", id);
     }
+
+    let text = get("text").map(|yaml_text| {
+        yaml_text
+            .expect_string("Event text should be a string.")
+            .clone()
+    });
+    let directives = match get("do") {
+        Some(yaml_directives) => yaml_directives
+            .expect_array("Event directives should be an array.")
+            .iter()
+            .map(parse_directive)
+            .collect(),
+        None => vec![],
+    };
+    let repeat = match get("repeat") {
+        Some(Yaml::Boolean(repeat)) => *repeat,
+        Some(_) => panic!("Event {} repeat should be true or false.", id),
+        None => false,
+    };
+
+    Event {
+        id,
+        trigger,
+        after,
+        text,
+        directives,
+        repeat,
+    }
+}
+
+// events live in <world>/events/
+pub fn load_events(dir: impl AsRef<Path>) -> Vec<Event> {
+    yaml_files(dir.as_ref().join("events"))
+        .iter()
+        .flat_map(|path| {
+            let source = fs::read_to_string(path).expect("Should be able to read from event file.");
+            let docs =
+                YamlLoader::load_from_str(&source).expect("Event file should be in YAML format.");
+            docs[0]
+                .expect_array("Event file should be a YAML array.")
+                .iter()
+                .map(parse_event)
+                .collect::<Vec<Event>>()
+        })
+        .collect()
+}
+
+// every .yaml or .yml file directly inside dir; subdirectories are left for other
+// loaders, and anything else (a README, an editor's backup file) is ignored
+pub fn yaml_files(dir: impl AsRef<Path>) -> Vec<std::path::PathBuf> {
+    let dir = dir.as_ref();
+    if !dir.is_dir() {
+        return vec![];
+    }
+    fs::read_dir(dir)
+        .unwrap()
+        .map(|path_result| path_result.expect("Path should exist.").path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| matches!(extension, "yaml" | "yml"))
+        })
+        .collect()
+}
+
+// voices live in <world>/voices/
+pub fn load_voices(dir: impl AsRef<Path>) -> Vec<Voice> {
+    yaml_files(dir.as_ref().join("voices"))
+        .iter()
+        .map(|path| {
+            parse_voice(
+                fs::read_to_string(path)
+                    .expect("Should be able to read from voice file.")
+                    .as_str(),
+            )
+        })
+        .collect()
 }
 
-fn load_locations() -> Vec<Location> {
-    let paths = fs::read_dir("assets/").unwrap();
-    paths
-        .map(|path_result| {
-            let path = path_result.expect("Path should exist.").path();
+// npcs live in <world>/npcs/
+pub fn load_npcs(dir: impl AsRef<Path>) -> Vec<Npc> {
+    yaml_files(dir.as_ref().join("npcs"))
+        .iter()
+        .map(|path| {
+            parse_npc(
+                fs::read_to_string(path)
+                    .expect("Should be able to read from NPC file.")
+                    .as_str(),
+            )
+        })
+        .collect()
+}
+
+// each file directly inside the world directory is a location, named after the file
+pub fn load_locations(dir: impl AsRef<Path>) -> Vec<Location> {
+    yaml_files(dir)
+        .into_iter()
+        .map(|path| {
             parse_location(
                 fs::read_to_string(&path)
                     .expect("Should be able to read from location file.")
@@ -238,7 +1190,356 @@ actions:
     - set isDoorOpen true
     - goto nextRoom
 ";
-        let locations = load_locations();
+        let location = parse_location(s, "test");
+        assert_eq!(location.description, "Lorem ipsum dolor sit amet.");
+        assert_eq!(location.actions[0].title, "unlock door");
+        assert_eq!(location.actions[0].directives.len(), 2);
+        let locations = load_locations("assets");
         println!("{:#?}", locations);
     }
+
+    #[test]
+    fn test_parse_player_property() {
+        assert_eq!(
+            parse_condition("$me.hasKey").unwrap().1.properties(),
+            vec!["$me.hasKey"]
+        );
+        assert!(matches!(
+            parse_directive_str("set $me.hasKey true"),
+            Ok(Directive::SetProperty(PropertyId(property), true)) if property == "$me.hasKey"
+        ));
+    }
+
+    #[test]
+    fn test_parse_fail_and_else() {
+        let s = "
+description: A test room.
+
+actions:
+- unlock door:
+    hasKey:
+    - set isDoorOpen true
+    fail: The door is locked.
+    else:
+    - set triedDoor true
+";
+        let location = parse_location(s, "test");
+        assert_eq!(
+            location.actions[0].fail.as_deref(),
+            Some("The door is locked.")
+        );
+        assert_eq!(location.actions[0].else_directives.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let s = "
+description: A test room.
+
+first_visit:
+  text: You have never been here before.
+on_exit:
+  do:
+  - set leftTest true
+";
+        let location = parse_location(s, "test");
+        assert!(location.first_visit.unwrap().text.is_some());
+        assert_eq!(location.on_exit.unwrap().directives.len(), 1);
+        assert!(location.on_enter.is_none());
+        assert!(matches!(
+            parse_condition("!visited_test").unwrap().1,
+            Condition::Not(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_exits() {
+        let s = "
+description: A test room.
+
+exits:
+- n: garden
+- down:
+    to: cellar
+    when: hasLantern
+    locked: It is far too dark to go down there.
+";
+        let location = parse_location(s, "test");
+        assert_eq!(location.exits.len(), 2);
+        assert_eq!(location.exits[0].direction, Direction::North);
+        assert_eq!(location.exits[0].target, LocationId("garden".into()));
+        assert_eq!(location.exits[1].direction, Direction::Down);
+        assert!(location.exits[1].locked_message.is_some());
+    }
+
+    #[test]
+    fn test_parse_objects() {
+        let location = parse_location(
+            "description: A test room.\n\nobjects:\n  door: A sturdy oak door.\n",
+            "test",
+        );
+        assert_eq!(location.objects[0].name, "door");
+    }
+
+    #[test]
+    fn test_yaml_files() {
+        let dir = std::env::temp_dir().join(format!("wwizard-yaml-files-{}", std::process::id()));
+        fs::create_dir_all(dir.join("npcs")).unwrap();
+        for name in ["attic.yaml", "cellar.yml", "README.md", "attic.yaml~"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let mut names: Vec<String> = yaml_files(&dir)
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().into())
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["attic.yaml", "cellar.yml"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "only key")]
+    fn test_exit_rejects_second_pair() {
+        let s = "
+description: A test room.
+
+exits:
+- east: hallway
+  west: garden
+";
+        parse_location(s, "test");
+    }
+
+    #[test]
+    #[should_panic(expected = "whole number from 1")]
+    fn test_random_rejects_bad_weight() {
+        let s = "
+random:
+- 4294967296:
+  - say \"Heads.\"
+";
+        parse_directive(&YamlLoader::load_from_str(s).unwrap()[0]);
+    }
+
+    #[test]
+    fn test_parse_directives() {
+        let parse = |s: &str| parse_directive(&YamlLoader::load_from_str(s).unwrap()[0]);
+
+        assert!(matches!(parse("toggle lampOn"), Directive::Toggle(_)));
+        assert!(matches!(parse("describe"), Directive::Describe));
+        assert!(matches!(parse("lose"), Directive::Lose(None)));
+        let Directive::Say(text) = parse(r#"say "The lamp hums.  Loudly.""#) else {
+            panic!("should be a say directive");
+        };
+        assert_eq!(text, "The lamp hums.  Loudly.");
+        let Directive::Win(Some(text)) = parse(r#"win "You escaped!""#) else {
+            panic!("should be a win directive");
+        };
+        assert_eq!(text, "You escaped!");
+
+        let Directive::Random(branches) = parse(
+            "
+random:
+- 3:
+  - say \"Heads.\"
+- 1:
+  - say \"Tails.\"
+  - toggle unlucky
+",
+        ) else {
+            panic!("should be a random directive");
+        };
+        assert_eq!(branches.len(), 2);
+        assert_eq!(branches[0].0, 3);
+        assert_eq!(branches[1].1.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "double quotes")]
+    fn test_say_needs_quotes() {
+        parse_directive(&Yaml::String("say hello".into()));
+    }
+
+    #[test]
+    #[should_panic(expected = "too many words")]
+    fn test_directive_extra_words() {
+        parse_directive(&Yaml::String("toggle a b".into()));
+    }
+
+    #[test]
+    fn test_parse_explicit_action() {
+        let s = "
+description: A test room.
+
+actions:
+- title: unlock door
+  aliases: [open door]
+  when: hasKey
+  do:
+  - set isDoorOpen true
+  fail: The door is locked.
+  once: true
+- wave:
+    always:
+    - say \"You wave.\"
+";
+        let location = parse_location(s, "test");
+        let explicit = &location.actions[0];
+        assert_eq!(explicit.title, "unlock door");
+        assert_eq!(explicit.aliases, vec!["open door".to_string()]);
+        assert!(matches!(explicit.condition, Condition::IsPropertyTrue(_)));
+        assert_eq!(explicit.directives.len(), 1);
+        assert!(explicit.else_directives.is_empty());
+        assert!(explicit.once);
+        assert!(!explicit.hidden);
+
+        let compact = &location.actions[1];
+        assert_eq!(compact.title, "wave");
+        assert!(compact.aliases.is_empty());
+        assert!(!compact.once);
+    }
+
+    #[test]
+    fn test_compact_action_titled_title() {
+        let s = "
+description: A test room.
+
+actions:
+- title:
+    always:
+    - say \"A Wizard's Tale.\"
+";
+        let location = parse_location(s, "test");
+        assert_eq!(location.actions[0].title, "title");
+        assert!(matches!(location.actions[0].condition, Condition::Always));
+        assert_eq!(location.actions[0].directives.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "single condition")]
+    fn test_compact_action_rejects_second_condition() {
+        let s = "
+description: A test room.
+
+actions:
+- unlock door:
+    hasKey:
+    - set isDoorOpen true
+    hasCrowbar:
+    - set isDoorBroken true
+";
+        parse_location(s, "test");
+    }
+
+    #[test]
+    fn test_parse_npc() {
+        let s = "
+name: Old Hob
+location: kitchen
+description: A grumpy house spirit.
+
+dialogue:
+  start:
+    text: What do you want?
+    options:
+    - Nothing.
+    - Have you seen my key?:
+        when: '!hasKey'
+        do:
+        - set askedHob true
+        goto: key
+  key:
+    text: Maybe.
+";
+        let npc = parse_npc(s);
+        assert_eq!(npc.location, LocationId("kitchen".into()));
+        let start = &npc.dialogue["start"];
+        assert_eq!(start.options.len(), 2);
+        assert!(start.options[0].next.is_none());
+        assert_eq!(start.options[1].directives.len(), 1);
+        assert_eq!(start.options[1].next.as_deref(), Some("key"));
+        assert!(npc.dialogue["key"].options.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "existing node")]
+    fn test_npc_rejects_missing_goto() {
+        let s = "
+name: Old Hob
+location: kitchen
+description: A grumpy house spirit.
+
+dialogue:
+  start:
+    text: What do you want?
+    options:
+    - Have you seen my key?:
+        goto: kye
+  key:
+    text: Maybe.
+";
+        parse_npc(s);
+    }
+
+    #[test]
+    #[should_panic(expected = "only key")]
+    fn test_dialogue_option_rejects_second_pair() {
+        let s = "
+name: Old Hob
+location: kitchen
+description: A grumpy house spirit.
+
+dialogue:
+  start:
+    text: What do you want?
+    options:
+    - Have you seen my key?:
+        goto: start
+      Nothing.:
+";
+        parse_npc(s);
+    }
+
+    #[test]
+    fn test_parse_voice() {
+        let voice = parse_voice("name: jesse\nstyle: red\nasides:\n  Exit: go away jesse\n");
+        assert_eq!(voice.name, "jesse");
+        assert_eq!(voice.style.as_deref(), Some("red"));
+        assert_eq!(voice.asides, vec![("exit".into(), "go away jesse".into())]);
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let s = "
+- bells:
+    after: 10
+    text: Somewhere, bells ring.
+- draught:
+    enter: hallway
+    repeat: true
+    do:
+    - set isCold true
+";
+        let docs = YamlLoader::load_from_str(s).unwrap();
+        let events: Vec<Event> = docs[0].as_vec().unwrap().iter().map(parse_event).collect();
+        assert!(matches!(events[0].trigger, Trigger::Start));
+        assert_eq!(events[0].after, 10);
+        assert!(!events[0].repeat);
+        assert!(matches!(&events[1].trigger, Trigger::Enter(LocationId(l)) if l == "hallway"));
+        assert!(events[1].repeat);
+        assert!(events[1].text.is_none());
+        assert!(!load_events("assets").is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "only key")]
+    fn test_event_rejects_second_pair() {
+        let s = "
+bells:
+  after: 10
+draught:
+  enter: hallway
+";
+        parse_event(&YamlLoader::load_from_str(s).unwrap()[0]);
+    }
 }
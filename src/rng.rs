@@ -0,0 +1,57 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// a small xorshift generator; plenty for picking random directive branches
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn seeded(seed: u64) -> Rng {
+        // xorshift gets stuck on zero
+        Rng(seed.max(1))
+    }
+
+    pub fn from_time() -> Rng {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0);
+        Rng::seeded(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    // index of the weight picked, with probability proportional to its size
+    pub fn pick_weighted(&mut self, weights: &[u32]) -> usize {
+        let total: u64 = weights.iter().map(|weight| *weight as u64).sum();
+        let mut roll = self.next_u64() % total.max(1);
+        for (i, weight) in weights.iter().enumerate() {
+            if roll < *weight as u64 {
+                return i;
+            }
+            roll -= *weight as u64;
+        }
+        weights.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_weighted() {
+        let mut rng = Rng::seeded(42);
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            counts[rng.pick_weighted(&[1, 0, 3])] += 1;
+        }
+        assert_eq!(counts[1], 0);
+        assert!(counts[2] > counts[0] * 2);
+    }
+}
@@ -0,0 +1,494 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tungstenite::{Error as WsError, Message as Frame, WebSocket};
+use wwizard::multiplayer::SharedGame;
+use wwizard::{cache::DescriptionCache, narrator, Ending, Engine, Output, World};
+
+use crate::app::{Message, MessageType};
+use crate::sessions::{self, Session, Sessions, Summary, IDLE_TIMEOUT};
+
+// the whole browser client: one page that opens a websocket back to /ws
+const CLIENT: &str = include_str!("serve.html");
+
+// longest request head we'll look at before deciding what a connection wants
+const MAX_HEAD: usize = 8192;
+
+// how often idle sessions are looked for
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+// how long a shared game connection waits for input before checking for news
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// what the players connecting to a server play
+enum Games {
+    // a game of their own each, kept across reconnects
+    Sessions(Sessions),
+    // one world, together
+    Shared {
+        world: Arc<World>,
+        game: Mutex<SharedGame>,
+    },
+}
+
+// serve the browser client over http, and play over websockets: one session per
+// browser, or with shared, one world everyone plays in together. each session has
+// its own App, so the browser sees exactly what the terminal would
+pub fn serve(addr: &str, world: Arc<World>, shared: bool) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("serving on http://{}", listener.local_addr()?);
+
+    let games = Arc::new(if shared {
+        Games::Shared {
+            world,
+            game: Mutex::new(SharedGame::new()),
+        }
+    } else {
+        Games::Sessions(Sessions::from_env(world))
+    });
+    if let Games::Sessions(_) = *games {
+        let sweeper = Arc::clone(&games);
+        thread::spawn(move || loop {
+            thread::sleep(SWEEP_INTERVAL);
+            if let Games::Sessions(sessions) = &*sweeper {
+                for id in sessions.sweep() {
+                    eprintln!("session {} timed out", id);
+                }
+            }
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("couldn't accept connection: {}", e);
+                continue;
+            }
+        };
+        let games = Arc::clone(&games);
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &games) {
+                eprintln!("connection closed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, games: &Games) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+    let head = peek_head(&stream)?;
+    let is_websocket = head
+        .lines()
+        .any(|line| line.to_ascii_lowercase().starts_with("upgrade: websocket"));
+
+    let path = head.split_whitespace().nth(1).unwrap_or("/");
+    match games {
+        Games::Sessions(sessions) if is_websocket => {
+            // a player who hasn't sent anything in this long is disconnected
+            stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+            play(stream, sessions, query_param(path, "session"))
+        }
+        Games::Shared { world, game } if is_websocket => {
+            stream.set_read_timeout(Some(POLL_INTERVAL))?;
+            play_shared(stream, world, game)
+        }
+        _ => serve_client(stream, &head, games),
+    }
+}
+
+// the request line and headers, left in the stream for whoever handles the request
+fn peek_head(stream: &TcpStream) -> io::Result<String> {
+    let mut buf = vec![0; MAX_HEAD];
+    let mut waited = 0;
+    loop {
+        let n = stream.peek(&mut buf)?;
+        let head = String::from_utf8_lossy(&buf[..n]);
+        if let Some(end) = head.find("\r\n\r\n") {
+            return Ok(head[..end].to_string());
+        }
+        if n == buf.len() || waited > 100 {
+            return Ok(head.into_owned());
+        }
+        // the rest of the headers haven't arrived yet
+        thread::sleep(Duration::from_millis(10));
+        waited += 1;
+    }
+}
+
+fn serve_client(mut stream: TcpStream, head: &str, games: &Games) -> io::Result<()> {
+    // take the request out of the stream so closing it doesn't reset the connection
+    let mut request = vec![0; head.len() + 4];
+    let _ = stream.read(&mut request)?;
+
+    let path = head.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = match path {
+        "/" | "/index.html" => ("200 OK", "text/html; charset=utf-8", CLIENT.to_string()),
+        // anyone who can reach the server can see this; bind to localhost to keep it private
+        "/admin" => (
+            "200 OK",
+            "text/html; charset=utf-8",
+            admin_page(&summaries(games)),
+        ),
+        _ => (
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "Not found.\n".to_string(),
+        ),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// one session: tell the browser its id, catch it up on everything said so far, then
+// submit every line it sends like a line typed into the terminal
+fn play(stream: TcpStream, sessions: &Sessions, id: Option<&str>) -> io::Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+    let (id, session) = sessions.join(id)?;
+    let result = play_session(&mut socket, sessions, &id, &session);
+    let left = sessions.leave(&id, &session);
+    result.and(left)
+}
+
+fn play_session(
+    socket: &mut WebSocket<TcpStream>,
+    sessions: &Sessions,
+    id: &str,
+    session: &Mutex<Session>,
+) -> io::Result<()> {
+    send(socket, session_json(id))?;
+    let mut sent = 0;
+    let mut ending_sent = false;
+
+    loop {
+        // take what's new, then let go of the session before writing to a socket that
+        // might be slow, so other connections to it aren't held up
+        let mut news = vec![];
+        let exit = {
+            let session = sessions::lock(session);
+            let app = &session.app;
+            news.extend(app.message_history[sent..].iter().map(message_json));
+            sent = app.message_history.len();
+            if let Some(ending) = &app.engine.game.ending {
+                if !ending_sent {
+                    news.push(ending_json(ending));
+                    ending_sent = true;
+                }
+            }
+            app.exit
+        };
+        for json in news {
+            send(socket, json)?;
+        }
+        if exit {
+            let _ = socket.close(None);
+            let _ = socket.flush();
+            return Ok(());
+        }
+
+        match socket.read() {
+            Ok(Frame::Text(line)) => {
+                let mut session = sessions::lock(session);
+                session.app.submit(line.trim(), MessageType::User);
+                session.last_active = Instant::now();
+                if let Err(e) = sessions.save(id, &session) {
+                    eprintln!("couldn't save session {}: {}", id, e);
+                }
+            }
+            Ok(Frame::Close(_)) | Err(WsError::ConnectionClosed) => return Ok(()),
+            Err(WsError::Io(e))
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                send(
+                    socket,
+                    status_json("[disconnected for being idle; reload to carry on]"),
+                )?;
+                let _ = socket.close(None);
+                let _ = socket.flush();
+                return Ok(());
+            }
+            Ok(_) => (),
+            Err(e) => return Err(io::Error::other(e)),
+        }
+    }
+}
+
+// a shared game: ask the player's name, then play in the same world as everyone else.
+// everything each player does happens under one lock, so a slow narrator holds up the
+// other players
+fn play_shared(stream: TcpStream, world: &Arc<World>, game: &Mutex<SharedGame>) -> io::Result<()> {
+    let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+    send(&mut socket, status_json("What's your name?"))?;
+
+    let mut last_heard = Instant::now();
+    let name = loop {
+        let name = match poll(&mut socket)? {
+            Poll::Line(line) => line,
+            Poll::Nothing if last_heard.elapsed() < IDLE_TIMEOUT => continue,
+            Poll::Nothing | Poll::Closed => return Ok(()),
+        };
+        last_heard = Instant::now();
+
+        let engine = Engine::new(
+            Arc::clone(world),
+            narrator::from_env()?,
+            DescriptionCache::from_env()?,
+        );
+        let joined = sessions::lock(game).join(&name, engine);
+        match joined {
+            Ok(outputs) => {
+                send_outputs(&mut socket, outputs)?;
+                break name.trim().to_string();
+            }
+            Err(e) => send(&mut socket, status_json(&e))?,
+        }
+    };
+
+    let result = play_shared_as(&mut socket, game, &name, last_heard);
+    sessions::lock(game).leave(&name);
+    let _ = socket.close(None);
+    let _ = socket.flush();
+    result
+}
+
+fn play_shared_as(
+    socket: &mut WebSocket<TcpStream>,
+    game: &Mutex<SharedGame>,
+    name: &str,
+    mut last_heard: Instant,
+) -> io::Result<()> {
+    loop {
+        match poll(socket)? {
+            Poll::Line(line) if line == "quit" => return Ok(()),
+            Poll::Line(line) => {
+                last_heard = Instant::now();
+                let (outputs, ending) = {
+                    let mut game = sessions::lock(game);
+                    let outputs = game.handle(name, &line);
+                    (
+                        outputs,
+                        game.game(name).and_then(|game| game.ending.clone()),
+                    )
+                };
+                let echo = Message {
+                    text: line,
+                    msg_type: MessageType::User,
+                };
+                send(socket, message_json(&echo))?;
+                send_outputs(socket, outputs)?;
+                if let Some(ending) = ending {
+                    return send(socket, ending_json(&ending));
+                }
+            }
+            Poll::Nothing if last_heard.elapsed() < IDLE_TIMEOUT => (),
+            Poll::Nothing => {
+                return send(socket, status_json("[disconnected for being idle]"));
+            }
+            Poll::Closed => return Ok(()),
+        }
+
+        let news = sessions::lock(game).take_inbox(name);
+        send_outputs(socket, news)?;
+    }
+}
+
+enum Poll {
+    Line(String),
+    // nothing arrived before the read timed out
+    Nothing,
+    Closed,
+}
+
+fn poll(socket: &mut WebSocket<TcpStream>) -> io::Result<Poll> {
+    match socket.read() {
+        Ok(Frame::Text(line)) => Ok(Poll::Line(line.trim().to_string())),
+        Ok(Frame::Close(_)) | Err(WsError::ConnectionClosed) => Ok(Poll::Closed),
+        Err(WsError::Io(e))
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(Poll::Nothing)
+        }
+        Ok(_) => Ok(Poll::Nothing),
+        Err(e) => Err(io::Error::other(e)),
+    }
+}
+
+fn send_outputs(socket: &mut WebSocket<TcpStream>, outputs: Vec<Output>) -> io::Result<()> {
+    for output in outputs {
+        let message = Message {
+            text: output.text().to_string(),
+            msg_type: MessageType::Game(output.category().to_string()),
+        };
+        send(socket, message_json(&message))?;
+    }
+    Ok(())
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, json: String) -> io::Result<()> {
+    socket.send(Frame::Text(json)).map_err(io::Error::other)
+}
+
+// {"type": "user", "text": "..."}, or {"type": "game", "category": "narrator", "text": "..."}.
+// the category (narrator, hint, system, error, or who's speaking) picks the message's
+// style in the client
+fn message_json(message: &Message) -> String {
+    match &message.msg_type {
+        MessageType::User => format!(
+            "{{\"type\":\"user\",\"text\":{}}}",
+            json_string(&message.text)
+        ),
+        MessageType::Game(category) => format!(
+            "{{\"type\":\"game\",\"category\":{},\"text\":{}}}",
+            json_string(category),
+            json_string(&message.text)
+        ),
+    }
+}
+
+fn session_json(id: &str) -> String {
+    format!("{{\"type\":\"session\",\"id\":{}}}", json_string(id))
+}
+
+fn status_json(text: &str) -> String {
+    format!("{{\"type\":\"status\",\"text\":{}}}", json_string(text))
+}
+
+fn ending_json(ending: &Ending) -> String {
+    let (won, message) = match ending {
+        Ending::Won(message) => (true, message.as_deref().unwrap_or("Well done!")),
+        Ending::Lost(message) => (
+            false,
+            message.as_deref().unwrap_or("Better luck next time."),
+        ),
+    };
+    format!(
+        "{{\"type\":\"ending\",\"won\":{},\"text\":{}}}",
+        won,
+        json_string(message)
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// "?a=1&b=2" -> a and b. values are used as-is; session ids don't need decoding
+fn query_param<'a>(path: &'a str, name: &str) -> Option<&'a str> {
+    let (_, query) = path.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+// a row on the admin page for every session in memory, or every player in a shared game
+fn summaries(games: &Games) -> Vec<Summary> {
+    match games {
+        Games::Sessions(sessions) => sessions.list(),
+        Games::Shared { game, .. } => sessions::lock(game)
+            .players()
+            .map(|(name, game)| Summary {
+                id: name.to_string(),
+                location: game.location.0.clone(),
+                turn: game.turn,
+                ended: game.ending.is_some(),
+                idle: None,
+                connections: 1,
+            })
+            .collect(),
+    }
+}
+
+// a table of every session in memory. refreshes itself every few seconds
+fn admin_page(summaries: &[Summary]) -> String {
+    let mut rows = String::new();
+    for summary in summaries {
+        rows.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            html_escape(&summary.id),
+            html_escape(&summary.location),
+            summary.turn,
+            if summary.ended { "yes" } else { "no" },
+            summary
+                .idle
+                .map_or("-".to_string(), |idle| format!("{}s", idle.as_secs())),
+            summary.connections
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"5\">\n<title>wwizard sessions</title>\n\
+         <style>body {{ font: 14px ui-monospace, monospace; }} td, th {{ padding: 0 1em; text-align: left; }}</style>\n\
+         </head>\n<body>\n<h1>{} session(s)</h1>\n<table>\n\
+         <tr><th>id</th><th>location</th><th>turn</th><th>ended</th><th>idle</th><th>connections</th></tr>\n\
+         {}</table>\n</body>\n</html>\n",
+        summaries.len(),
+        rows
+    )
+}
+
+pub fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_json() {
+        let message = Message {
+            text: "She says \"hi\".\nThen\tleaves.".into(),
+            msg_type: MessageType::Game("Marigold".into()),
+        };
+        assert_eq!(
+            message_json(&message),
+            r#"{"type":"game","category":"Marigold","text":"She says \"hi\".\nThen\tleaves."}"#
+        );
+        assert_eq!(
+            ending_json(&Ending::Lost(None)),
+            r#"{"type":"ending","won":false,"text":"Better luck next time."}"#
+        );
+    }
+
+    #[test]
+    fn test_query_param() {
+        assert_eq!(query_param("/ws?session=abc123", "session"), Some("abc123"));
+        assert_eq!(query_param("/ws?x=1&session=abc", "session"), Some("abc"));
+        assert_eq!(query_param("/ws", "session"), None);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use wwizard::{cache::DescriptionCache, narrator, Engine, World};
+
+use crate::app::App;
+
+// where session saves go unless WWIZARD_SAVES says otherwise
+const DEFAULT_SAVES_DIR: &str = ".wwizard-saves";
+
+// how much of a session's id the admin page shows: enough to tell sessions apart,
+// not enough to join one
+const SHOWN_ID_LEN: usize = 6;
+
+// a session nobody has touched for this long is saved and dropped from memory
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+// one player's game, and the frontend state that goes with it
+pub struct Session {
+    pub app: App,
+    pub last_active: Instant,
+    // open connections playing this session
+    pub connections: usize,
+}
+
+// what the admin page shows about a session
+pub struct Summary {
+    // for a session, only the start of its id
+    pub id: String,
+    pub location: String,
+    pub turn: u32,
+    pub ended: bool,
+    // None if nobody keeps track
+    pub idle: Option<Duration>,
+    pub connections: usize,
+}
+
+// every game being played on a server. all of them share one loaded world; each has
+// its own Game, saved to disk after every turn so it survives restarts and idle timeouts
+pub struct Sessions {
+    world: Arc<World>,
+    // None to keep sessions in memory only
+    saves: Option<PathBuf>,
+    idle_timeout: Duration,
+    new_engine: fn(Arc<World>) -> io::Result<Engine>,
+    sessions: Mutex<HashMap<String, Arc<Mutex<Session>>>>,
+}
+
+impl Sessions {
+    pub fn new(
+        world: Arc<World>,
+        saves: Option<PathBuf>,
+        idle_timeout: Duration,
+        new_engine: fn(Arc<World>) -> io::Result<Engine>,
+    ) -> Sessions {
+        Sessions {
+            world,
+            saves,
+            idle_timeout,
+            new_engine,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // saves go in WWIZARD_SAVES: a directory, or "off" to not save at all.
+    // engines get their narrator and cache from the environment, like the terminal app's
+    pub fn from_env(world: Arc<World>) -> Sessions {
+        let saves = match env::var("WWIZARD_SAVES") {
+            Ok(setting) if setting == "off" => None,
+            Ok(dir) => Some(PathBuf::from(dir)),
+            Err(_) => Some(PathBuf::from(DEFAULT_SAVES_DIR)),
+        };
+        Sessions::new(world, saves, IDLE_TIMEOUT, |world| {
+            Ok(Engine::new(
+                world,
+                narrator::from_env()?,
+                DescriptionCache::from_env()?,
+            ))
+        })
+    }
+
+    // the session with this id, if it's in memory or saved; otherwise a new game with
+    // a new id. either way, counts as a connection until leave is called
+    pub fn join(&self, id: Option<&str>) -> io::Result<(String, Arc<Mutex<Session>>)> {
+        let id = id.filter(|id| is_valid_id(id));
+
+        if let Some(id) = id {
+            let mut sessions = lock(&self.sessions);
+            if let Some(session) = sessions.get(id).cloned() {
+                let mut guard = lock(&session);
+                // the player quit this one; start over rather than rejoin it
+                if guard.app.exit {
+                    drop(guard);
+                    sessions.remove(id);
+                } else {
+                    guard.connections += 1;
+                    guard.last_active = Instant::now();
+                    drop(guard);
+                    return Ok((id.to_string(), session));
+                }
+            }
+        }
+
+        // starting an engine can be slow (the narrator may have to start), so don't
+        // hold up every other session while it happens
+        let mut engine = (self.new_engine)(Arc::clone(&self.world))?;
+        let (id, app) = match id.and_then(|id| self.read_save(id)) {
+            Some((id, save)) => {
+                let outputs = engine.restore(&save)?;
+                (id, App::with_outputs(engine, outputs))
+            }
+            None => (new_id()?, App::new(engine)),
+        };
+
+        let session = Arc::new(Mutex::new(Session {
+            app,
+            last_active: Instant::now(),
+            connections: 0,
+        }));
+        let session = Arc::clone(lock(&self.sessions).entry(id.clone()).or_insert(session));
+        lock(&session).connections += 1;
+        Ok((id, session))
+    }
+
+    // call when a connection playing a session closes
+    pub fn leave(&self, id: &str, session: &Arc<Mutex<Session>>) -> io::Result<()> {
+        let mut guard = lock(session);
+        guard.connections = guard.connections.saturating_sub(1);
+        guard.last_active = Instant::now();
+        self.save(id, &guard)
+    }
+
+    // write a session's game to its save file. a game that's over, or that the player
+    // quit, has nothing worth resuming, so its save is removed instead
+    pub fn save(&self, id: &str, session: &Session) -> io::Result<()> {
+        let Some(dir) = &self.saves else {
+            return Ok(());
+        };
+        let path = dir.join(format!("{}.yaml", id));
+        if session.app.exit || session.app.engine.game.ending.is_some() {
+            return match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        fs::create_dir_all(dir)?;
+        fs::write(path, session.app.engine.save()?)
+    }
+
+    // drop every session nobody is connected to that has been idle too long or was
+    // quit, saving each first. returns the ids dropped
+    pub fn sweep(&self) -> Vec<String> {
+        let mut sessions = lock(&self.sessions);
+        let idle: Vec<String> = sessions
+            .iter()
+            .filter(|(_, session)| {
+                let session = lock(session);
+                session.connections == 0
+                    && (session.app.exit || session.last_active.elapsed() >= self.idle_timeout)
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &idle {
+            if let Some(session) = sessions.remove(id) {
+                if let Err(e) = self.save(id, &lock(&session)) {
+                    eprintln!("couldn't save session {}: {}", id, e);
+                }
+            }
+        }
+        idle
+    }
+
+    // every session in memory, most recently active first
+    pub fn list(&self) -> Vec<Summary> {
+        let mut summaries: Vec<Summary> = lock(&self.sessions)
+            .iter()
+            .map(|(id, session)| {
+                let session = lock(session);
+                let game = &session.app.engine.game;
+                Summary {
+                    id: format!("{}…", &id[..SHOWN_ID_LEN.min(id.len())]),
+                    location: game.location.0.clone(),
+                    turn: game.turn,
+                    ended: game.ending.is_some() || session.app.exit,
+                    idle: Some(session.last_active.elapsed()),
+                    connections: session.connections,
+                }
+            })
+            .collect();
+        summaries.sort_by_key(|summary| summary.idle);
+        summaries
+    }
+
+    fn read_save(&self, id: &str) -> Option<(String, String)> {
+        let path = self.saves.as_ref()?.join(format!("{}.yaml", id));
+        let save = fs::read_to_string(path).ok()?;
+        Some((id.to_string(), save))
+    }
+}
+
+// an id is all it takes to play someone's session, so it has to be unguessable: 128
+// bits from the OS, not the game's rng
+fn new_id() -> io::Result<String> {
+    let mut bytes = [0; 16];
+    fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    Ok(bytes.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+// ids end up in file names, so only accept the kind new_id makes
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty() && id.len() <= 32 && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+// a panic while one session was being played shouldn't take the others down with it
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::MessageType;
+    use wwizard::narrator::OfflineNarrator;
+
+    fn offline_engine(world: Arc<World>) -> io::Result<Engine> {
+        Ok(Engine::new(
+            world,
+            Box::new(OfflineNarrator),
+            DescriptionCache::default(),
+        ))
+    }
+
+    #[test]
+    fn test_sessions() {
+        let saves = env::temp_dir().join(format!("wwizard-saves-{}", std::process::id()));
+        let world = Arc::new(World::load("assets"));
+        let sessions = Sessions::new(
+            Arc::clone(&world),
+            Some(saves.clone()),
+            Duration::ZERO,
+            offline_engine,
+        );
+
+        // unknown and malformed ids get a fresh game
+        let (id, session) = sessions.join(Some("../../etc/passwd")).unwrap();
+        assert!(is_valid_id(&id));
+        assert_eq!(id.len(), 32);
+        lock(&session).app.submit("go east", MessageType::User);
+
+        // the same id joins the same game while it's in memory
+        let (same_id, same) = sessions.join(Some(&id)).unwrap();
+        assert_eq!(same_id, id);
+        assert!(Arc::ptr_eq(&session, &same));
+        assert_eq!(sessions.list()[0].connections, 2);
+        // the admin page doesn't give ids away
+        assert_eq!(sessions.list()[0].id, format!("{}…", &id[..SHOWN_ID_LEN]));
+        sessions.leave(&id, &session).unwrap();
+        sessions.leave(&id, &same).unwrap();
+
+        // idle and disconnected, so it's dropped, but its save brings it back
+        assert_eq!(sessions.sweep(), vec![id.clone()]);
+        assert!(sessions.list().is_empty());
+        let (resumed_id, resumed) = sessions.join(Some(&id)).unwrap();
+        assert_eq!(resumed_id, id);
+        assert_eq!(lock(&resumed).app.engine.game.location.0, "hallway");
+
+        fs::remove_dir_all(saves).unwrap();
+    }
+}
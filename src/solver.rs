@@ -0,0 +1,377 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::engine::{World, MAX_CHAINED_MOVES};
+use crate::game::{Ending, Game};
+use crate::parser::{Directive, Hook, Location};
+
+// stop exploring past this many states; worlds with many independent flags blow up fast
+const MAX_STATES: usize = 100_000;
+
+// everything about a game that decides what can happen next. turns and events aren't
+// part of it, so the solver doesn't see anything that only happens with time
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct State {
+    pub location: String,
+    // properties that are true; false and unset are the same thing
+    pub properties: BTreeSet<String>,
+    pub used_actions: BTreeSet<String>,
+}
+
+// what the solver found out about a world
+pub struct Report {
+    pub states: usize,
+    // the search stopped at MAX_STATES, so the rest of the report is incomplete
+    pub truncated: bool,
+    // location -> the shortest commands that get there
+    pub locations: BTreeMap<String, Vec<String>>,
+    // locations with files that can't be reached from the start
+    pub unreachable: Vec<String>,
+    // each distinct ending, and the shortest commands that reach it
+    pub endings: Vec<(Ending, Vec<String>)>,
+    // states from which no ending can be reached, with the commands that get there
+    pub dead_ends: Vec<(State, Vec<String>)>,
+}
+
+// breadth-first search over every state the player can get the game into, trying every
+// exit and every action in each. random directives branch into all of their outcomes
+pub fn solve(world: &World) -> Report {
+    let solver = Solver { world };
+
+    let mut game = Game::new(world.start.clone());
+    solver.arrive(&mut game);
+
+    let mut states: Vec<State> = vec![State::of(&game)];
+    let mut seen: HashMap<State, usize> = HashMap::from([(states[0].clone(), 0)]);
+    // index -> (parent index, command that led here)
+    let mut parents: Vec<Option<(usize, String)>> = vec![None];
+    // index -> indices of the states its moves lead to; usize::MAX for an ending
+    let mut successors: Vec<Vec<usize>> = vec![vec![]];
+    let mut endings: Vec<(Ending, usize)> = vec![];
+    let mut queue = VecDeque::from([(0, game)]);
+
+    let mut truncated = false;
+    while let Some((index, game)) = queue.pop_front() {
+        for (command, next) in solver.moves(&game) {
+            if let Some(ending) = &next.ending {
+                if !endings.iter().any(|(seen, _)| seen == ending) {
+                    // an ending is reached from this state by this command; remember it
+                    // as a pseudo-state so the path can be rebuilt
+                    states.push(State::of(&next));
+                    parents.push(Some((index, command)));
+                    successors.push(vec![]);
+                    endings.push((ending.clone(), states.len() - 1));
+                }
+                successors[index].push(usize::MAX);
+                continue;
+            }
+
+            let state = State::of(&next);
+            let next_index = match seen.get(&state) {
+                Some(&next_index) => next_index,
+                None => {
+                    if seen.len() >= MAX_STATES {
+                        truncated = true;
+                        continue;
+                    }
+                    let next_index = states.len();
+                    seen.insert(state.clone(), next_index);
+                    states.push(state);
+                    parents.push(Some((index, command)));
+                    successors.push(vec![]);
+                    queue.push_back((next_index, next));
+                    next_index
+                }
+            };
+            if next_index != index {
+                successors[index].push(next_index);
+            }
+        }
+    }
+
+    let path = |mut index: usize| {
+        let mut commands = vec![];
+        while let Some((parent, command)) = &parents[index] {
+            commands.push(command.clone());
+            index = *parent;
+        }
+        commands.reverse();
+        commands
+    };
+
+    // bfs order means the first state found in each location has the shortest path
+    let mut reached: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for index in seen.values().copied().collect::<BTreeSet<_>>() {
+        reached
+            .entry(states[index].location.clone())
+            .or_insert_with(|| path(index));
+    }
+    let unreachable = world
+        .locations
+        .iter()
+        .map(|location| location.title.clone())
+        .filter(|title| !reached.contains_key(title))
+        .collect();
+
+    // a state can end the game if it has an ending move, or a successor that can
+    let mut can_end: HashSet<usize> = HashSet::new();
+    loop {
+        let before = can_end.len();
+        for index in seen.values() {
+            if successors[*index]
+                .iter()
+                .any(|next| *next == usize::MAX || can_end.contains(next))
+            {
+                can_end.insert(*index);
+            }
+        }
+        if can_end.len() == before {
+            break;
+        }
+    }
+    let mut dead_ends: Vec<(State, Vec<String>)> = seen
+        .values()
+        .filter(|index| !can_end.contains(index))
+        .map(|index| (states[*index].clone(), path(*index)))
+        .collect();
+    dead_ends.sort_by(|(a, a_path), (b, b_path)| a_path.len().cmp(&b_path.len()).then(a.cmp(b)));
+
+    Report {
+        states: seen.len(),
+        truncated,
+        locations: reached,
+        unreachable,
+        endings: endings
+            .into_iter()
+            .map(|(ending, index)| (ending, path(index)))
+            .collect(),
+        dead_ends,
+    }
+}
+
+impl State {
+    fn of(game: &Game) -> State {
+        State {
+            location: game.location.0.clone(),
+            properties: game
+                .properties
+                .iter()
+                .filter(|(_, value)| **value)
+                .map(|(property_id, _)| property_id.clone())
+                .collect(),
+            used_actions: game.used_actions.iter().cloned().collect(),
+        }
+    }
+}
+
+// plays out directives the way the app does, minus anything the player only sees
+struct Solver<'a> {
+    world: &'a World,
+}
+
+impl Solver<'_> {
+    // every command worth trying here, and each game it can lead to
+    fn moves(&self, game: &Game) -> Vec<(String, Game)> {
+        let Some(location) = self.world.location(&game.location) else {
+            return vec![];
+        };
+
+        let mut moves = vec![];
+        for exit in &location.exits {
+            if game.evaluate(&exit.condition) {
+                for next in self.apply(game.clone(), &[Directive::GoTo(exit.target.clone())]) {
+                    moves.push((exit.direction.to_string(), next));
+                }
+            }
+        }
+        for action in &location.actions {
+            let (directives, used) = if game.is_enabled(action) {
+                (&action.directives, action.once)
+            } else {
+                (&action.else_directives, false)
+            };
+            let mut game = game.clone();
+            if used {
+                game.used_actions.insert(game.action_key(action));
+            }
+            for next in self.apply(game, directives) {
+                moves.push((action.title.clone(), next));
+            }
+        }
+        moves
+    }
+
+    // like Engine::apply: run the directives, then arrive if the player moved
+    fn apply(&self, game: Game, directives: &[Directive]) -> Vec<Game> {
+        let previous_location = game.location.clone();
+        self.apply_each(game, directives)
+            .into_iter()
+            .map(|mut game| {
+                if game.location != previous_location {
+                    self.arrive(&mut game);
+                }
+                game
+            })
+            .collect()
+    }
+
+    fn apply_each(&self, game: Game, directives: &[Directive]) -> Vec<Game> {
+        let mut games = vec![game];
+        for directive in directives {
+            games = games
+                .into_iter()
+                .flat_map(|mut game| match directive {
+                    Directive::GoTo(target) if *target != game.location => {
+                        self.run_hook(&mut game, |location| &location.on_exit);
+                        game.update(directive);
+                        vec![game]
+                    }
+                    Directive::Random(branches) => branches
+                        .iter()
+                        .flat_map(|(_, directives)| self.apply_each(game.clone(), directives))
+                        .collect(),
+                    _ => {
+                        game.update(directive);
+                        vec![game]
+                    }
+                })
+                .collect();
+        }
+        games
+    }
+
+    // like Engine::arrive_until_settled: a hook that moves the player on cuts the
+    // arrival short, and the player arrives again wherever they end up
+    fn arrive(&self, game: &mut Game) {
+        for _ in 0..MAX_CHAINED_MOVES {
+            let here = game.location.clone();
+            let visited = format!("visited_{}", here.0);
+            if !game.properties.get(&visited).unwrap_or(&false) {
+                self.run_hook(game, |location| &location.first_visit);
+            }
+            game.properties.insert(visited, true);
+            if game.location == here {
+                self.run_hook(game, |location| &location.on_enter);
+            }
+            if game.location == here {
+                return;
+            }
+        }
+    }
+
+    // hooks don't branch: a random directive in a hook takes its first branch
+    fn run_hook(&self, game: &mut Game, hook: impl Fn(&Location) -> &Option<Hook>) {
+        let Some(Hook { directives, .. }) = self
+            .world
+            .location(&game.location)
+            .and_then(|location| hook(location).clone())
+        else {
+            return;
+        };
+        if let Some(next) = self.apply_each(game.clone(), &directives).into_iter().next() {
+            *game = next;
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let commands = |path: &[String]| match path {
+            [] => "(start)".to_string(),
+            _ => path.join(", "),
+        };
+
+        writeln!(f, "explored {} states", self.states)?;
+        if self.truncated {
+            writeln!(
+                f,
+                "stopped after {} states; the rest of this report is incomplete",
+                MAX_STATES
+            )?;
+        }
+
+        writeln!(f, "\nreachable locations:")?;
+        for (location, path) in &self.locations {
+            writeln!(f, "  {}: {}", location, commands(path))?;
+        }
+        if !self.unreachable.is_empty() {
+            writeln!(f, "\nunreachable locations:")?;
+            for location in &self.unreachable {
+                writeln!(f, "  {}", location)?;
+            }
+        }
+
+        writeln!(f, "\nendings:")?;
+        if self.endings.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for (ending, path) in &self.endings {
+            let (kind, message) = match ending {
+                Ending::Won(message) => ("won", message),
+                Ending::Lost(message) => ("lost", message),
+            };
+            match message {
+                Some(message) => writeln!(f, "  {} ({}): {}", kind, message, commands(path))?,
+                None => writeln!(f, "  {}: {}", kind, commands(path))?,
+            }
+        }
+
+        writeln!(f, "\ndead ends:")?;
+        if self.dead_ends.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for (state, path) in &self.dead_ends {
+            writeln!(f, "  in {}: {}", state.location, commands(path))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::LocationId;
+
+    #[test]
+    fn test_solve_assets() {
+        let report = solve(&World::load("assets"));
+        assert!(!report.truncated);
+
+        assert_eq!(report.locations["bedroom"], Vec::<String>::new());
+        assert_eq!(report.locations["hallway"], vec!["east"]);
+        // hell doesn't exist, but the player can still end up there
+        assert_eq!(
+            report.locations["hell"],
+            vec!["read letter", "east", "recall information from that letter"]
+        );
+        assert!(report.unreachable.is_empty());
+
+        assert_eq!(report.endings.len(), 1);
+        assert!(matches!(report.endings[0].0, Ending::Lost(_)));
+        assert_eq!(report.endings[0].1, vec!["jump out of window"]);
+
+        // and once there, nothing can be done
+        assert_eq!(report.dead_ends.len(), 1);
+        assert_eq!(report.dead_ends[0].0.location, "hell");
+    }
+
+    #[test]
+    fn test_circular_hooks() {
+        let mut world = World::load("assets");
+        for (from, to) in [("bedroom", "hallway"), ("hallway", "bedroom")] {
+            let location = world
+                .locations
+                .iter_mut()
+                .find(|location| location.title == from)
+                .unwrap();
+            location.on_enter = Some(Hook {
+                text: None,
+                directives: vec![Directive::GoTo(LocationId(to.into()))],
+            });
+        }
+
+        // finishes instead of overflowing the stack
+        let report = solve(&world);
+        assert!(!report.locations.is_empty());
+    }
+}
@@ -0,0 +1,366 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use ratatui::style::{Color, Modifier, Style};
+use wwizard::{cache::DescriptionCache, narrator, Ending, Engine, World};
+
+use crate::app::{App, Message, MessageType};
+use crate::config::Theme;
+use crate::sessions::IDLE_TIMEOUT;
+use crate::ui::wrap_line;
+
+// telnet commands (rfc 854) and the one option we ask for: negotiate about window
+// size (rfc 1073), so text wraps to fit each client
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const NAWS: u8 = 31;
+
+// until the client says otherwise
+const DEFAULT_WIDTH: usize = 80;
+
+// longest line (or subnegotiation) a client can send before it's disconnected
+const MAX_LINE: usize = 4096;
+
+// play over plain tcp, one game per connection: `telnet localhost 4000`, or nc
+pub fn serve(addr: &str, world: Arc<World>) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    eprintln!("serving telnet on {}", listener.local_addr()?);
+
+    // every client gets the default colours, and the world's for its voices
+    let mut theme = Theme::default();
+    theme.add_voices(&world);
+    let theme = Arc::new(theme);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("couldn't accept connection: {}", e);
+                continue;
+            }
+        };
+        let world = Arc::clone(&world);
+        let theme = Arc::clone(&theme);
+        thread::spawn(move || {
+            if let Err(e) = play(stream, world, &theme) {
+                eprintln!("connection closed: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+// one game: each line the client sends is submitted like a line typed into the
+// terminal, and every message the app posts is written back in colour
+fn play(mut stream: TcpStream, world: Arc<World>, theme: &Theme) -> io::Result<()> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    stream.write_all(&[IAC, DO, NAWS])?;
+
+    let engine = Engine::new(world, narrator::from_env()?, DescriptionCache::from_env()?);
+    let mut app = App::new(engine);
+    let mut telnet = Telnet::default();
+    let mut sent = 0;
+    let mut buf = [0; 1024];
+
+    loop {
+        let mut out = String::new();
+        for message in &app.message_history[sent..] {
+            // the client already shows what its player typed
+            if message.msg_type != MessageType::User {
+                out.push_str(&render(message, telnet.width(), theme));
+            }
+        }
+        sent = app.message_history.len();
+        if let Some(ending) = &app.engine.game.ending {
+            out.push_str(&render_ending(ending));
+            stream.write_all(out.as_bytes())?;
+            return Ok(());
+        }
+        if app.exit {
+            return Ok(());
+        }
+        out.push_str("> ");
+        stream.write_all(out.as_bytes())?;
+
+        let mut lines = vec![];
+        while lines.is_empty() {
+            let n = match stream.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    stream.write_all(b"\r\n[disconnected for being idle]\r\n")?;
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            };
+            let (received, replies) = telnet.feed(&buf[..n]);
+            if telnet.overflowed {
+                stream.write_all(b"\r\n[disconnected for sending too long a line]\r\n")?;
+                return Ok(());
+            }
+            stream.write_all(&replies)?;
+            lines = received;
+        }
+        for line in lines {
+            if !line.trim().is_empty() {
+                app.submit(line.trim(), MessageType::User);
+            }
+        }
+    }
+}
+
+// where a connection is in the telnet byte stream
+#[derive(Default, Clone, Copy, PartialEq)]
+enum State {
+    #[default]
+    Data,
+    // just after an IAC
+    Command,
+    // after IAC WILL/WONT/DO/DONT, waiting for the option
+    Option(u8),
+    // inside IAC SB ... IAC SE
+    Subnegotiation,
+    // an IAC inside a subnegotiation
+    SubnegotiationIac,
+}
+
+// splits what a telnet client sends into lines of text, answering (and mostly
+// refusing) option negotiation along the way
+#[derive(Default)]
+struct Telnet {
+    state: State,
+    line: Vec<u8>,
+    subnegotiation: Vec<u8>,
+    // columns the client has, or 0 if it hasn't said
+    columns: usize,
+    // the last byte ended a line with \r, so a \n or \0 after it is part of the same end
+    after_cr: bool,
+    // a line or subnegotiation went past MAX_LINE; nothing more is read
+    overflowed: bool,
+}
+
+impl Telnet {
+    // the complete lines in bytes, and anything to send back to the client
+    fn feed(&mut self, bytes: &[u8]) -> (Vec<String>, Vec<u8>) {
+        let mut lines = vec![];
+        let mut replies = vec![];
+        for &byte in bytes {
+            let after_cr = std::mem::take(&mut self.after_cr);
+            self.state = match (self.state, byte) {
+                (State::Data, IAC) => State::Command,
+                // telnet ends lines with \r\n or \r\0, nc with just \n
+                (State::Data, b'\n' | 0) if after_cr => State::Data,
+                (State::Data, b'\r' | b'\n') => {
+                    let line = std::mem::take(&mut self.line);
+                    lines.push(String::from_utf8_lossy(&line).into_owned());
+                    self.after_cr = byte == b'\r';
+                    State::Data
+                }
+                (State::Data, 0) => State::Data,
+                (State::Data, byte) => {
+                    self.line.push(byte);
+                    State::Data
+                }
+                (State::Command, IAC) => {
+                    self.line.push(IAC);
+                    State::Data
+                }
+                (State::Command, WILL | WONT | DO | DONT) => State::Option(byte),
+                (State::Command, SB) => {
+                    self.subnegotiation.clear();
+                    State::Subnegotiation
+                }
+                // go ahead, are you there, and the rest don't matter to a line server
+                (State::Command, _) => State::Data,
+                (State::Option(verb), option) => {
+                    match (verb, option) {
+                        // the client agreeing to send its size is all we asked for
+                        (WILL, NAWS) | (WONT, _) | (DONT, _) => (),
+                        (WILL, option) => replies.extend([IAC, DONT, option]),
+                        (DO, option) => replies.extend([IAC, WONT, option]),
+                        _ => (),
+                    }
+                    State::Data
+                }
+                (State::Subnegotiation, IAC) => State::SubnegotiationIac,
+                (State::Subnegotiation, byte) => {
+                    self.subnegotiation.push(byte);
+                    State::Subnegotiation
+                }
+                (State::SubnegotiationIac, SE) => {
+                    self.end_subnegotiation();
+                    State::Data
+                }
+                // a doubled IAC is a 255 data byte
+                (State::SubnegotiationIac, byte) => {
+                    self.subnegotiation.push(byte);
+                    State::Subnegotiation
+                }
+            };
+            if self.line.len() > MAX_LINE || self.subnegotiation.len() > MAX_LINE {
+                self.overflowed = true;
+                break;
+            }
+        }
+        (lines, replies)
+    }
+
+    // NAWS width(2 bytes) height(2 bytes)
+    fn end_subnegotiation(&mut self) {
+        if let [NAWS, w0, w1, ..] = self.subnegotiation[..] {
+            self.columns = u16::from_be_bytes([w0, w1]) as usize;
+        }
+    }
+
+    // columns to wrap text at
+    fn width(&self) -> usize {
+        match self.columns {
+            // some clients send 0 when they don't know
+            0 => DEFAULT_WIDTH,
+            // leave the last column free, so terminals don't wrap a full line again
+            columns => (columns - 1).max(20),
+        }
+    }
+}
+
+// a message in its category's style (the same ones ui uses), wrapped to fit,
+// followed by a blank line
+fn render(message: &Message, width: usize, theme: &Theme) -> String {
+    let text = message
+        .text
+        .split('\n')
+        .map(|line| wrap_line(line.to_string(), width))
+        .collect::<Vec<String>>()
+        .join("\n");
+    format!(
+        "\x1b[{}m{}\x1b[0m\r\n\r\n",
+        sgr(theme.message(&message.msg_type)),
+        text.replace('\n', "\r\n")
+    )
+}
+
+// the ANSI select graphic rendition parameters for a style, like "1;96"
+fn sgr(style: Style) -> String {
+    let mut params: Vec<String> = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+    ]
+    .into_iter()
+    .filter(|(modifier, _)| style.add_modifier.contains(*modifier))
+    .map(|(_, param)| param.to_string())
+    .collect();
+    let colour = match style.fg {
+        None | Some(Color::Reset) => None,
+        Some(Color::Black) => Some("30".to_string()),
+        Some(Color::Red) => Some("31".to_string()),
+        Some(Color::Green) => Some("32".to_string()),
+        Some(Color::Yellow) => Some("33".to_string()),
+        Some(Color::Blue) => Some("34".to_string()),
+        Some(Color::Magenta) => Some("35".to_string()),
+        Some(Color::Cyan) => Some("36".to_string()),
+        Some(Color::Gray) => Some("37".to_string()),
+        Some(Color::DarkGray) => Some("90".to_string()),
+        Some(Color::LightRed) => Some("91".to_string()),
+        Some(Color::LightGreen) => Some("92".to_string()),
+        Some(Color::LightYellow) => Some("93".to_string()),
+        Some(Color::LightBlue) => Some("94".to_string()),
+        Some(Color::LightMagenta) => Some("95".to_string()),
+        Some(Color::LightCyan) => Some("96".to_string()),
+        Some(Color::White) => Some("97".to_string()),
+        Some(Color::Indexed(n)) => Some(format!("38;5;{}", n)),
+        Some(Color::Rgb(r, g, b)) => Some(format!("38;2;{};{};{}", r, g, b)),
+    };
+    params.extend(colour);
+    // an empty list resets, which is what a plain style means anyway
+    params.join(";")
+}
+
+fn render_ending(ending: &Ending) -> String {
+    let (title, message, colour) = match ending {
+        Ending::Won(message) => ("YOU WIN", message.as_deref().unwrap_or("Well done!"), "92"),
+        Ending::Lost(message) => (
+            "THE END",
+            message.as_deref().unwrap_or("Better luck next time."),
+            "91",
+        ),
+    };
+    format!(
+        "\x1b[1;{}m*** {} ***\x1b[0m\r\n{}\r\n",
+        colour, title, message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_telnet_lines_and_naws() {
+        let mut telnet = Telnet::default();
+        assert_eq!(telnet.width(), DEFAULT_WIDTH);
+
+        // agreeing to NAWS then reporting 100x40, split across reads
+        let (lines, replies) = telnet.feed(&[IAC, WILL, NAWS, IAC, SB, NAWS, 0, 100]);
+        assert!(lines.is_empty() && replies.is_empty());
+        telnet.feed(&[0, 40, IAC, SE]);
+        assert_eq!(telnet.width(), 99);
+
+        // other options are refused
+        let (_, replies) = telnet.feed(&[IAC, WILL, 1, IAC, DO, 3]);
+        assert_eq!(replies, vec![IAC, DONT, 1, IAC, WONT, 3]);
+
+        let (lines, _) = telnet.feed(b"go ea");
+        assert!(lines.is_empty());
+        let (lines, _) = telnet.feed(b"st\r\nlook\r\0wait\n");
+        assert_eq!(lines, vec!["go east", "look", "wait"]);
+        assert!(!telnet.overflowed);
+
+        // a client that never ends its line
+        telnet.feed(&[b'a'; MAX_LINE + 1]);
+        assert!(telnet.overflowed);
+    }
+
+    #[test]
+    fn test_render() {
+        let message = Message {
+            text: "a b c".into(),
+            msg_type: MessageType::narrator(),
+        };
+        let mut theme = Theme::default();
+        assert_eq!(
+            render(&message, 3, &theme),
+            "\x1b[96ma \r\nb c\x1b[0m\r\n\r\n"
+        );
+        assert_eq!(wrap_line("déjà vu, naïve".into(), 5), "déjà \nvu, \nnaïve");
+        assert_eq!(wrap_line("ééééééé".into(), 3), "ééé\nééé\né");
+
+        // the world's voices bring their own styles
+        theme.add_voices(&World::load("assets"));
+        let message = Message {
+            text: "go away jesse".into(),
+            msg_type: MessageType::Game("jesse".into()),
+        };
+        assert!(render(&message, 80, &theme).starts_with("\x1b[31m"));
+        assert_eq!(
+            sgr(Style::new()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Rgb(1, 2, 3))),
+            "1;38;2;1;2;3"
+        );
+    }
+}
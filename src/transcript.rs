@@ -0,0 +1,207 @@
+use std::path::Path;
+
+use wwizard::engine::{ERROR, HINT, NARRATOR, SYSTEM};
+
+use crate::app::{Message, MessageType};
+use crate::serve::html_escape;
+
+// what `export transcript` can write
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Html,
+    Markdown,
+    Text,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "html" | "htm" => Some(Format::Html),
+            "markdown" | "md" => Some(Format::Markdown),
+            "text" | "txt" | "plain" => Some(Format::Text),
+            _ => None,
+        }
+    }
+
+    // the format a file name suggests
+    pub fn from_path(path: &Path) -> Option<Format> {
+        Format::from_name(&path.extension()?.to_str()?.to_lowercase())
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Html => "html",
+            Format::Markdown => "md",
+            Format::Text => "txt",
+        }
+    }
+}
+
+// everything in the message pane, in order, keeping who said what: the player's
+// commands, narration, hints and system messages, and each speaker's lines
+pub fn render(messages: &[Message], format: Format) -> String {
+    match format {
+        Format::Html => html(messages),
+        Format::Markdown => markdown(messages),
+        Format::Text => text(messages),
+    }
+}
+
+// how a message is introduced in the plain formats, e.g. "> " or "[hint] " or
+// "jesse: ". npcs already name themselves ("Marigold: ..."), so they aren't named twice
+fn label(message: &Message) -> String {
+    match &message.msg_type {
+        MessageType::User => "> ".into(),
+        MessageType::Game(category) if category == NARRATOR => String::new(),
+        MessageType::Game(category) if [HINT, SYSTEM, ERROR].contains(&category.as_str()) => {
+            format!("[{}] ", category)
+        }
+        MessageType::Game(speaker) if message.text.starts_with(&format!("{}:", speaker)) => {
+            String::new()
+        }
+        MessageType::Game(speaker) => format!("{}: ", speaker),
+    }
+}
+
+fn text(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| format!("{}{}\n", label(message), message.text))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn markdown(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|message| {
+            let text = markdown_escape(&message.text);
+            let label = markdown_escape(&label(message));
+            let paragraph = match &message.msg_type {
+                MessageType::User => format!("**{}{}**", label, text),
+                MessageType::Game(category) if category == NARRATOR => text,
+                MessageType::Game(category)
+                    if [HINT, SYSTEM, ERROR].contains(&category.as_str()) =>
+                {
+                    format!("*{}{}*", label, text)
+                }
+                MessageType::Game(_) if label.is_empty() => text,
+                MessageType::Game(_) => format!("**{}** {}", label.trim_end(), text),
+            };
+            // keep the message's own line breaks
+            format!("{}\n", paragraph.replace('\n', "  \n"))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// backslash anything markdown would read as formatting, including a number and a dot
+// at the start of a line (dialogue options), which would become a list
+fn markdown_escape(s: &str) -> String {
+    s.split('\n')
+        .map(|line| {
+            let mut escaped = String::new();
+            for c in line.chars() {
+                if "\\`*_[]<>#|".contains(c) {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+            }
+            let digits = escaped
+                .trim_start()
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .count();
+            let indent = escaped.len() - escaped.trim_start().len();
+            if digits > 0 && escaped[indent + digits..].starts_with('.') {
+                escaped.insert(indent + digits, '\\');
+            }
+            escaped
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// a page styled like the web client's, where each message's class is its category
+// and every speaker's lines also carry data-speaker
+fn html(messages: &[Message]) -> String {
+    let mut body = String::new();
+    for message in messages {
+        let category = message.msg_type.category();
+        let (class, speaker) = match category {
+            "user" | NARRATOR | HINT | SYSTEM | ERROR => (category, String::new()),
+            speaker => (
+                "speech",
+                format!(" data-speaker=\"{}\"", html_escape(speaker)),
+            ),
+        };
+        body.push_str(&format!(
+            "<p class=\"message {}\"{}>{}{}</p>\n",
+            class,
+            speaker,
+            if message.msg_type == MessageType::User {
+                "&gt; "
+            } else {
+                ""
+            },
+            html_escape(&message.text)
+        ));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>wwizard transcript</title>\n<style>\n\
+         body {{ background: #000; color: #ccc; font: 16px ui-monospace, monospace; max-width: 80ch; margin: 2em auto; }}\n\
+         .message {{ white-space: pre-wrap; margin: 0 0 1em; }}\n\
+         .user {{ color: #fff; }}\n.narrator {{ color: #7ff; }}\n.speech {{ color: #ff5; }}\n\
+         .hint {{ color: #0cc; }}\n.system {{ color: #aaa; }}\n.error {{ color: #f77; }}\n\
+         </style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(category: Option<&str>, text: &str) -> Message {
+        Message {
+            text: text.into(),
+            msg_type: category.map_or(MessageType::User, |category| {
+                MessageType::Game(category.into())
+            }),
+        }
+    }
+
+    #[test]
+    fn test_render_transcript() {
+        let messages = vec![
+            message(None, "talk to marigold"),
+            message(Some("Marigold"), "Marigold: Hello!\n  1. Hi"),
+            message(Some("jesse"), "go away *jesse*"),
+            message(Some(HINT), "Exits: west."),
+            message(Some(NARRATOR), "A <long> hallway."),
+        ];
+
+        assert_eq!(
+            render(&messages, Format::Text),
+            "> talk to marigold\n\nMarigold: Hello!\n  1. Hi\n\njesse: go away *jesse*\n\n\
+             [hint] Exits: west.\n\nA <long> hallway.\n"
+        );
+        assert_eq!(
+            render(&messages, Format::Markdown),
+            "**\\> talk to marigold**\n\nMarigold: Hello!  \n  1\\. Hi\n\n\
+             **jesse:** go away \\*jesse\\*\n\n*\\[hint\\] Exits: west.*\n\nA \\<long\\> hallway.\n"
+        );
+        let html = render(&messages, Format::Html);
+        assert!(html.contains("<p class=\"message user\">&gt; talk to marigold</p>"));
+        assert!(
+            html.contains("<p class=\"message speech\" data-speaker=\"jesse\">go away *jesse*</p>")
+        );
+        assert!(html.contains("<p class=\"message narrator\">A &lt;long&gt; hallway.</p>"));
+
+        assert_eq!(
+            Format::from_path(Path::new("run.MD")),
+            Some(Format::Markdown)
+        );
+    }
+}
@@ -1,19 +1,22 @@
 use ratatui::{
-    layout::{Constraint, Layout, Position, Rect}, style::{Color, Style}, symbols::border::Set, text::Text, widgets::{Block, Borders, List, ListDirection, Paragraph}, Frame
+    layout::{Alignment, Constraint, Layout, Position, Rect}, style::Style, text::{Line, Span}, widgets::{Block, Clear, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap}, Frame
 };
 
-use crate::app::{App, MessageType};
+use crate::app::{App, Button, Mode};
+use crate::config::{KeyAction, Theme};
+use wwizard::Ending;
 
 // redraw the frame 
 pub fn ui(frame: &mut Frame, app: &mut App) {
 
     let area = frame.area();
+    let theme = app.config.theme.clone();
 
     // =========================================================================================
     // SETUP CENTER AREA
 
-    let h_pad = area.width / 20;
-    let v_pad = area.height / 15;
+    let h_pad = (area.width as f32 * theme.horizontal_padding) as u16;
+    let v_pad = (area.height as f32 * theme.vertical_padding) as u16;
 
     let area_ctr = Rect::new(
         h_pad,
@@ -22,56 +25,168 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
         area.height - 2 * v_pad
     );
 
+    // what can be done here, as buttons between the messages and the input box
+    let commands = match app.engine.game.ending {
+        Some(_) => vec![],
+        None    => app.engine.exit_directions()
+            .into_iter()
+            .map(|direction| format!("go {}", direction))
+            .chain(app.engine.available_actions().into_iter().map(String::from))
+            .collect()
+    };
+    let mut buttons = layout_buttons(commands, area_ctr.width);
+    let button_rows = buttons.iter().map(|button| button.area.y + 1).max().unwrap_or(0);
+
     let [
         text_area, 
+        buttons_area,
         input_area
-    ] = Layout::vertical([Constraint::Min(0), Constraint::Max(3)]).areas(area_ctr);
+    ] = Layout::vertical([Constraint::Min(0), Constraint::Length(button_rows), Constraint::Max(3)]).areas(area_ctr);
 
 
     // =========================================================================================
     // TEXT AREA
 
-    let text_area_block = Block::bordered()
-        .borders(Borders::TOP)
-        .border_set(Set {
-            top_left: "#",
-            top_right: "",
-            bottom_left: "",
-            bottom_right: "",
-            vertical_left: "",
-            vertical_right: "",
-            horizontal_top: "#",
-            horizontal_bottom: "",
-        })
-        .border_style(Style::new().fg(Color::LightBlue));
+    // a row of the border glyph, drawn as a title since border sets only take static strs
+    let border = theme.border_glyph.repeat(text_area.width as usize);
+    let text_area_block = Block::new()
+        .title(Line::styled(border, theme.border));
 
     let inner_text_area = text_area_block.inner(text_area);
 
-    let mut messages: Vec<Text> = vec![];
-    for message in app.message_history.clone().into_iter().rev() {
-        let color = match message.msg_type {
-            MessageType::User => Color::White,
-            MessageType::Game => Color::LightCyan,
-            MessageType::Jesse => Color::Red
-        };
-       let text = wrap_line(message.text, inner_text_area.width.into());
+    // the last column is the scrollbar's
+    let [messages_area, scrollbar_area] = Layout::horizontal([Constraint::Min(0), Constraint::Length(1)]).areas(inner_text_area);
+    let width = (messages_area.width as usize).max(1);
+    let height = messages_area.height as usize;
+
+    // every message, wrapped, with a blank line after each
+    let mut lines: Vec<Line> = vec![];
+    let mut selected_lines = None;
+    for (i, message) in app.message_history.iter().enumerate() {
+        let mut style = theme.message(&message.msg_type);
+        if app.selected == Some(i) {
+            style = style.patch(theme.selection);
+        }
+        // what's being searched for stands out wherever it is
+        let search = if app.matches_search(message) { app.search.as_str() } else { "" };
 
-        messages.push(Text::from(format!("{}\n\n", text)).style(color));
+        let start = lines.len();
+        // wrap each line separately so messages can contain their own line breaks
+        for line in message.text.split('\n') {
+            for wrapped in wrap_line(line.to_string(), width).split('\n') {
+                lines.push(highlight(wrapped, search, theme.search_match).style(style));
+            }
+        }
+        if app.selected == Some(i) {
+            selected_lines = Some(start..lines.len());
+        }
+        lines.push(Line::default());
+    }
+
+    // pass these to app so it knows where scrolling stops
+    app.message_lines = lines.len();
+    app.message_height = height;
+
+    // bring a newly selected message into view
+    if std::mem::take(&mut app.reveal_selected) {
+        if let Some(selected) = selected_lines {
+            let top = app.scroll_top();
+            if selected.start < top {
+                app.scroll_to(selected.start);
+            } else if selected.end > top + height {
+                app.scroll_to(selected.end.saturating_sub(height).min(selected.start));
+            }
+        }
     }
 
-    let messages = List::new(messages).direction(ListDirection::BottomToTop);
+    // only what's in view, sitting at the bottom of the pane until there's enough to fill it
+    let top = app.scroll_top();
+    let shown = lines.len().min(height);
+    let visible: Vec<Line> = lines.drain(top..top + shown).collect();
+    let visible_area = Rect {
+        y: messages_area.y + (height - shown) as u16,
+        height: shown as u16,
+        ..messages_area
+    };
 
     frame.render_widget(text_area_block, text_area);
-    frame.render_stateful_widget(messages, inner_text_area, &mut app.message_scroll_state);
+    frame.render_widget(Paragraph::new(visible), visible_area);
+
+    if app.message_lines > height {
+        let mut scrollbar_state = ScrollbarState::new(app.message_lines - height + 1)
+            .position(top)
+            .viewport_content_length(height);
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .style(theme.border);
+        frame.render_stateful_widget(scrollbar, scrollbar_area, &mut scrollbar_state);
+    }
+
+    // scrolled back while more arrived
+    if app.has_new_messages() && height > 0 {
+        let label = " new messages below \u{2193} ";
+        let label_width = (label.chars().count() as u16).min(messages_area.width);
+        let indicator = Rect::new(
+            messages_area.right() - label_width,
+            messages_area.bottom() - 1,
+            label_width,
+            1
+        );
+        frame.render_widget(Clear, indicator);
+        frame.render_widget(Paragraph::new(label).style(theme.selection), indicator);
+        app.new_messages_area = Some(indicator);
+    } else {
+        app.new_messages_area = None;
+    }
+
+    // =========================================================================================
+    // BUTTONS
+
+    for button in buttons.iter_mut() {
+        button.area.x += buttons_area.x;
+        button.area.y += buttons_area.y;
+        let hovered = app.hover.is_some_and(|position| button.area.contains(position));
+        let style = if hovered { theme.hover } else { theme.button };
+        frame.render_widget(Paragraph::new(format!(" {} ", button.command)).style(style), button.area);
+    }
+    // pass these to app so it knows what was clicked
+    app.buttons = buttons;
+    app.input_area = input_area;
+
+    // =========================================================================================
+    // ENDING SCREEN: replaces the input box once the game is over
+
+    if let Some(ending) = &app.engine.game.ending {
+        let leave = app.config.keys.describe(KeyAction::LeaveEnding);
+        render_ending(frame, ending, leave.as_deref(), area_ctr, &theme);
+        return;
+    }
 
     // =========================================================================================
     // INPUT AREA
 
-    let iw = Paragraph::new(app.input_str.as_str())
-        .block(Block::bordered().border_style(Style::new().fg(Color::LightBlue))
+    // while searching, the box holds the search instead
+    let (input, cursor) = match app.mode {
+        Mode::Search => (app.search.as_str(), app.search.chars().count()),
+        _            => (app.input_str.as_str(), app.input_char_index)
+    };
+    let title = match (&app.status, app.mode) {
+        (Some(status), _) => format!(" {} ", status),
+        (None, Mode::Search) => match app.match_position() {
+            (0, _)              => " search: no matches ".to_string(),
+            (n, Some(current))  => format!(" search: {} of {} ", current, n),
+            (n, None)           => format!(" search: {} matches ", n)
+        },
+        (None, Mode::Select) => " select: enter copies ".to_string(),
+        (None, Mode::Input)  => String::new()
+    };
+
+    let iw = Paragraph::new(input)
+        .block(Block::bordered().title(title).border_style(theme.border)
     );
     frame.set_cursor_position(Position::new(
-        input_area.x + app.input_char_index as u16 + 1,
+        input_area.x + cursor as u16 + 1,
         input_area.y + 1
     ));
 
@@ -82,25 +197,96 @@ pub fn ui(frame: &mut Frame, app: &mut App) {
     frame.render_widget(iw, input_area);
 }
 
-// Given a string and a max width, return a string with newlines inserted
-// to fit within the width. Finds the closest whitespace character to the
-// width threshold and newlines there. If none, newline at the width.
-fn wrap_line(text: String, width: usize) -> String {
-    if text.len() <= width {
-        return text;
-    } else {
-        let mut s = "".to_string();
-        for (i, c) in text[..width].chars().rev().enumerate() {
-            if c == ' ' {
-                s.push_str(&text[..(width - i)]);
-                s.push('\n');
-                s.push_str(&wrap_line(text[(width - i)..].to_string(), width));
-                return s;
-            }
+// at most this many rows of buttons; any that don't fit are left out
+const MAX_BUTTON_ROWS: u16 = 2;
+
+// a button for each command, left to right in rows of the given width, positioned
+// from (0, 0). each is the command with a space either side, and a space between them
+fn layout_buttons(commands: Vec<String>, width: u16) -> Vec<Button> {
+    let mut buttons = vec![];
+    let (mut x, mut y) = (0, 0);
+    for command in commands {
+        let button_width = command.chars().count() as u16 + 2;
+        if button_width > width {
+            continue;
+        }
+        if x > 0 && x + button_width > width {
+            x = 0;
+            y += 1;
+        }
+        if y >= MAX_BUTTON_ROWS {
+            break;
         }
-        s.push_str(&text[..(width)]);
-        s.push('\n');
-        s.push_str(&wrap_line(text[(width)..].to_string(), width));
-        return s;
+        buttons.push(Button { area: Rect::new(x, y, button_width, 1), command });
+        x += button_width + 1;
+    }
+    buttons
+}
+
+// a box in the middle of the screen announcing how the game ended, and which keys leave it
+fn render_ending(frame: &mut Frame, ending: &Ending, leave: Option<&str>, area: Rect, theme: &Theme) {
+    let (title, message, style) = match ending {
+        Ending::Won(message) => (" YOU WIN ", message.as_deref().unwrap_or("Well done!"), theme.won),
+        Ending::Lost(message) => (" THE END ", message.as_deref().unwrap_or("Better luck next time."), theme.lost),
+    };
+
+    let width = area.width.min(60);
+    let height = 7.min(area.height);
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height
+    );
+
+    let text = match leave {
+        Some(keys) => format!("\n{}\n\n(press {} to leave)", message, keys),
+        None => format!("\n{}", message),
+    };
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(
+            Block::bordered()
+                .title(title)
+                .title_alignment(Alignment::Center)
+                .border_style(style)
+        );
+
+    frame.render_widget(Clear, popup);
+    frame.render_widget(paragraph, popup);
+}
+
+// a line with every (ascii case-insensitive) occurrence of search in it picked out
+fn highlight(line: &str, search: &str, style: Style) -> Line<'static> {
+    if search.is_empty() {
+        return Line::from(line.to_string());
+    }
+    let haystack = line.to_ascii_lowercase();
+    let needle = search.to_ascii_lowercase();
+    let mut spans = vec![];
+    let mut start = 0;
+    while let Some(found) = haystack[start..].find(&needle) {
+        let found = start + found;
+        spans.push(Span::raw(line[start..found].to_string()));
+        spans.push(Span::styled(line[found..found + needle.len()].to_string(), style));
+        start = found + needle.len();
     }
+    spans.push(Span::raw(line[start..].to_string()));
+    Line::from(spans)
+}
+
+// Given a string and a max width in chars, return a string with newlines
+// inserted to fit within the width. Finds the closest whitespace character to
+// the width threshold and newlines there. If none, newline at the width.
+pub fn wrap_line(text: String, width: usize) -> String {
+    // where the first char past the width starts, if the text goes that far
+    let Some((end, _)) = text.char_indices().nth(width.max(1)) else {
+        return text;
+    };
+    let split = text[..end].rfind(' ').map_or(end, |space| space + 1);
+    let mut s = text[..split].to_string();
+    s.push('\n');
+    s.push_str(&wrap_line(text[split..].to_string(), width));
+    s
 }
\ No newline at end of file
@@ -0,0 +1,129 @@
+use crate::command::{Command, NounPhrase};
+use crate::parser::{Direction, PLAYER_SCOPE};
+
+// commands the engine understands on its own, without a location action or the narrator.
+// a location can still override any of these with an action that matches the same command
+#[derive(Debug, PartialEq)]
+pub enum Verb {
+    Go(Direction),
+    Look,
+    Examine(NounPhrase),
+    Talk(NounPhrase),
+    Inventory,
+    Help,
+    Wait,
+    Again,
+}
+
+impl Verb {
+    pub fn from_command(command: &Command) -> Option<Verb> {
+        // none of the built-in verbs take an indirect object
+        if command.indirect.is_some() {
+            return None;
+        }
+
+        let verb = match (command.verb.as_str(), &command.object) {
+            ("go", Some(phrase)) if phrase.adjectives.is_empty() => {
+                Verb::Go(Direction::from_word(&phrase.noun)?)
+            }
+            ("look", None) => Verb::Look,
+            // "check your inventory", as help suggests
+            ("examine", Some(phrase))
+                if phrase.adjectives.is_empty() && phrase.noun == "inventory" =>
+            {
+                Verb::Inventory
+            }
+            ("look" | "examine", Some(phrase)) => Verb::Examine(phrase.clone()),
+            ("talk", Some(phrase)) => Verb::Talk(phrase.clone()),
+            ("inventory", None) => Verb::Inventory,
+            ("help", None) => Verb::Help,
+            ("wait", None) => Verb::Wait,
+            ("again", None) => Verb::Again,
+            _ => return None,
+        };
+        Some(verb)
+    }
+}
+
+// "hasDeepInsightsAndKnowledge" -> "deep insights and knowledge".
+// properties named has<Thing> (or $me.has<Thing>) are how worlds track what the player
+// is carrying
+pub fn inventory_name(property_id: &str) -> Option<String> {
+    let thing = property_id
+        .strip_prefix(PLAYER_SCOPE)
+        .unwrap_or(property_id)
+        .strip_prefix("has")?;
+    if !thing.starts_with(|c: char| c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    let mut name = String::new();
+    for c in thing.chars() {
+        if c.is_ascii_uppercase() && !name.is_empty() {
+            name.push(' ');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    Some(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Option<Verb> {
+        Verb::from_command(&Command::parse(input)?)
+    }
+
+    #[test]
+    fn test_parse_verbs() {
+        assert_eq!(parse("north"), Some(Verb::Go(Direction::North)));
+        assert_eq!(parse("n"), Some(Verb::Go(Direction::North)));
+        assert_eq!(parse("go North"), Some(Verb::Go(Direction::North)));
+        assert_eq!(parse("go"), None);
+        // "in" is a preposition everywhere else
+        assert_eq!(parse("go in"), Some(Verb::Go(Direction::In)));
+        assert_eq!(parse("go out"), Some(Verb::Go(Direction::Out)));
+        assert_eq!(parse("walk out"), Some(Verb::Go(Direction::Out)));
+        assert_eq!(parse("enter"), Some(Verb::Go(Direction::In)));
+        assert_eq!(parse("go north quickly"), None);
+        assert_eq!(parse("read letter"), None);
+
+        assert_eq!(parse("L"), Some(Verb::Look));
+        assert_eq!(
+            parse("x the lamp"),
+            Some(Verb::Examine(NounPhrase::new("lamp")))
+        );
+        assert_eq!(
+            parse("look at glass baubles"),
+            Some(Verb::Examine(NounPhrase::new("glass baubles")))
+        );
+        assert_eq!(parse("examine"), None);
+        assert_eq!(
+            parse("talk to the old wizard"),
+            Some(Verb::Talk(NounPhrase::new("old wizard")))
+        );
+        assert_eq!(
+            parse("talk with marigold"),
+            Some(Verb::Talk(NounPhrase::new("marigold")))
+        );
+        assert_eq!(
+            parse("speak with marigold"),
+            Some(Verb::Talk(NounPhrase::new("marigold")))
+        );
+        assert_eq!(parse("g"), Some(Verb::Again));
+        assert_eq!(parse("check my inventory"), Some(Verb::Inventory));
+    }
+
+    #[test]
+    fn test_inventory_name() {
+        assert_eq!(inventory_name("hasKey"), Some("key".into()));
+        assert_eq!(inventory_name("$me.hasKey"), Some("key".into()));
+        assert_eq!(
+            inventory_name("hasDeepInsightsAndKnowledge"),
+            Some("deep insights and knowledge".into())
+        );
+        assert_eq!(inventory_name("hastily"), None);
+        assert_eq!(inventory_name("didReadLetter"), None);
+    }
+}